@@ -1,4 +1,6 @@
-use crate::lexer::Token;
+use std::cmp::Ordering;
+use std::fmt;
+use crate::span::Span;
 
 #[derive(Debug, Clone)]
 pub enum BinOp {
@@ -12,75 +14,481 @@ pub enum BinOp {
     Gte,
     Eq,
     Neq,
+    Cons,
+}
+
+impl BinOp {
+    /// The operator as written in the source.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Lt => "<",
+            BinOp::Lte => "<=",
+            BinOp::Gt => ">",
+            BinOp::Gte => ">=",
+            BinOp::Eq => "=",
+            BinOp::Neq => "<>",
+            BinOp::Cons => "::",
+        }
+    }
+}
+
+/// Which side an infix operator groups to when chained, e.g. `a - b - c`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Assoc {
+    Left,
+    Right,
+}
+
+/// The precedence (0 to 9, higher binds tighter) and associativity of an
+/// infix operator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fixity {
+    pub prec: u8,
+    pub assoc: Assoc,
+}
+
+impl fmt::Display for Fixity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.assoc {
+            Assoc::Left => write!(f, "infixl {}", self.prec),
+            Assoc::Right => write!(f, "infixr {}", self.prec),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum LiteralValue {
     Integer(String),
+    Real(String),
     Boolean(String),
     String(String),
+    Char(String),
 }
 
+/// Exceptions raised by the interpreter itself: `Div` for division by zero,
+/// `Overflow` when an integer result does not fit, `Match` when no arm of a
+/// match fits and `Bind` when a `val` pattern fails.
+pub const BUILTIN_EXCEPTIONS: [&str; 4] = ["Div", "Overflow", "Match", "Bind"];
+
 #[derive(Debug, Clone)]
 pub enum AstNode {
-    Program(Vec<Box<AstNode>>),
+    Program(Vec<Box<AstNode>>, Span),
     ValDecl {
         pat: AstPattern,
-        typ: Option<Type>,
+        typ: Option<TypeAnnotation>,
         exp: Box<AstNode>,
+        span: Span,
+    },
+    DatatypeDecl {
+        name: String,
+        params: Vec<String>,
+        constructors: Vec<ConstructorDecl>,
+        span: Span,
+    },
+    /// `exception Name of ty`, a new constructor of type `exn`.
+    ExceptionDecl {
+        name: String,
+        arg: Option<Type>,
+        span: Span,
+    },
+    /// `fun f ... and g ...` or `val rec f = fn ...`: functions which are
+    /// all in scope in each other's bodies.
+    FunGroup {
+        funs: Vec<FunBinding>,
+        span: Span,
+    },
+    /// `local private in public end`: the bindings of `private` are only
+    /// visible to `public`, whose bindings are visible afterwards.
+    Local {
+        private: Vec<Box<AstNode>>,
+        public: Vec<Box<AstNode>>,
+        span: Span,
+    },
+    /// `infix 5 @`, which only changes how the parser reads later code.
+    FixityDecl {
+        names: Vec<String>,
+        fixity: Fixity,
+        span: Span,
     },
     FunDecl {
         name: String,
         clauses: Vec<(AstPattern, Box<AstNode>)>,
-        typ: Option<Type>,
+        typ: Option<TypeAnnotation>,
+        span: Span,
     },
     If {
         cond: Box<AstNode>,
         then: Box<AstNode>,
         else_: Box<AstNode>,
+        span: Span,
     },
     Let {
-        decl: Box<AstNode>,
+        decls: Vec<Box<AstNode>>,
         body: Box<AstNode>,
+        span: Span,
     },
     Fn {
         clauses: Vec<(AstPattern, Box<AstNode>)>,
+        span: Span,
+    },
+    Case {
+        scrutinee: Box<AstNode>,
+        arms: Vec<(AstPattern, Box<AstNode>)>,
+        span: Span,
     },
     BinOp {
         left: Box<AstNode>,
         op: BinOp,
         right: Box<AstNode>,
+        span: Span,
+    },
+    /// Prefix negation `~e`.
+    Neg {
+        expr: Box<AstNode>,
+        span: Span,
+    },
+    /// `left andalso right`, which only evaluates `right` if `left` is true.
+    AndAlso {
+        left: Box<AstNode>,
+        right: Box<AstNode>,
+        span: Span,
+    },
+    /// `left orelse right`, which only evaluates `right` if `left` is false.
+    OrElse {
+        left: Box<AstNode>,
+        right: Box<AstNode>,
+        span: Span,
     },
     App {
         func: Box<AstNode>,
         arg: Box<AstNode>,
+        span: Span,
     },
-    Id(String),
-    Var(String),
-    Tuple(Vec<Box<AstNode>>),
-    List(Vec<Box<AstNode>>),
-    Literal(LiteralValue),
+    Raise {
+        expr: Box<AstNode>,
+        span: Span,
+    },
+    /// Allocate a new mutable cell holding `e`: the body of the built-in
+    /// function `ref`.
+    Ref {
+        expr: Box<AstNode>,
+        span: Span,
+    },
+    /// The contents of the cell `e`: the body of the built-in function `!`.
+    Deref {
+        expr: Box<AstNode>,
+        span: Span,
+    },
+    /// `target := value`, which updates the cell `target`.
+    Assign {
+        target: Box<AstNode>,
+        value: Box<AstNode>,
+        span: Span,
+    },
+    /// `expr handle pat => e | ...`, whose arms match the exception raised
+    /// while evaluating `expr`, if any.
+    Handle {
+        expr: Box<AstNode>,
+        arms: Vec<(AstPattern, Box<AstNode>)>,
+        span: Span,
+    },
+    /// `{label = e, ...}`, with the fields in source order, which is the
+    /// order they are evaluated in.
+    Record {
+        fields: Vec<(String, Box<AstNode>)>,
+        span: Span,
+    },
+    /// `#label`, the function taking that field of a record or tuple.
+    Selector {
+        label: String,
+        span: Span,
+    },
+    Id(String, Span),
+    Var(String, Span),
+    Tuple(Vec<Box<AstNode>>, Span),
+    List(Vec<Box<AstNode>>, Span),
+    /// `(e1; e2; e3)`: evaluates each in turn and yields the last.
+    Seq(Vec<Box<AstNode>>, Span),
+    Literal(LiteralValue, Span),
+    /// Placeholder for code that failed to parse.
+    Error(Span),
 }
 
-impl AstNode {}
+impl AstNode {
+    /// The built-in `datatype 'a option = none | some of 'a`. Passes that
+    /// track constructors visit it before the program.
+    pub fn option_datatype() -> AstNode {
+        let span = Span::default();
+        AstNode::DatatypeDecl {
+            name: "option".to_string(),
+            params: vec!["a".to_string()],
+            constructors: vec![
+                ConstructorDecl { name: "none".to_string(), arg: None, span },
+                ConstructorDecl { name: "some".to_string(), arg: Some(Type::Var("a".to_string())), span },
+            ],
+            span,
+        }
+    }
+
+    /// Declarations of the built-in exceptions, such as `Div` which integer
+    /// division by zero raises.
+    pub fn builtin_exceptions() -> AstNode {
+        let span = Span::default();
+        let decls = BUILTIN_EXCEPTIONS
+            .iter()
+            .map(|name| Box::new(AstNode::ExceptionDecl { name: name.to_string(), arg: None, span }))
+            .collect();
+        AstNode::Program(decls, span)
+    }
+
+    /// Declarations of the built-in functions that are written in tinyml
+    /// itself, such as `not`.
+    pub fn builtin_functions() -> AstNode {
+        let span = Span::default();
+        let id = |name: &str| Box::new(AstNode::Id(name.to_string(), span));
+        let boolean = |b: bool| Box::new(AstNode::Literal(LiteralValue::Boolean(b.to_string()), span));
+        let function = |name: &str, param: &str, body: AstNode| Box::new(AstNode::ValDecl {
+            pat: AstPattern::Id(name.to_string(), span),
+            typ: None,
+            exp: Box::new(AstNode::Fn {
+                clauses: vec![(AstPattern::Id(param.to_string(), span), Box::new(body))],
+                span,
+            }),
+            span,
+        });
+        let decls = vec![
+            // fun not b = if b then false else true
+            function("not", "b", AstNode::If { cond: id("b"), then: boolean(false), else_: boolean(true), span }),
+            // val ref = fn x => ref x, with the primitive that allocates the cell
+            function("ref", "x", AstNode::Ref { expr: id("x"), span }),
+            // val ! = fn r => !r, with the primitive that reads the cell
+            function("!", "r", AstNode::Deref { expr: id("r"), span }),
+        ];
+        AstNode::Program(decls, span)
+    }
+
+    pub fn span(&self) -> &Span {
+        match self {
+            AstNode::Program(_, span)
+            | AstNode::ValDecl { span, .. }
+            | AstNode::FunDecl { span, .. }
+            | AstNode::DatatypeDecl { span, .. }
+            | AstNode::FixityDecl { span, .. }
+            | AstNode::ExceptionDecl { span, .. }
+            | AstNode::Raise { span, .. }
+            | AstNode::Ref { span, .. }
+            | AstNode::Deref { span, .. }
+            | AstNode::Assign { span, .. }
+            | AstNode::Handle { span, .. }
+            | AstNode::FunGroup { span, .. }
+            | AstNode::Local { span, .. }
+            | AstNode::If { span, .. }
+            | AstNode::Let { span, .. }
+            | AstNode::Fn { span, .. }
+            | AstNode::Case { span, .. }
+            | AstNode::BinOp { span, .. }
+            | AstNode::Neg { span, .. }
+            | AstNode::AndAlso { span, .. }
+            | AstNode::OrElse { span, .. }
+            | AstNode::App { span, .. }
+            | AstNode::Record { span, .. }
+            | AstNode::Selector { span, .. }
+            | AstNode::Id(_, span)
+            | AstNode::Var(_, span)
+            | AstNode::Tuple(_, span)
+            | AstNode::List(_, span)
+            | AstNode::Seq(_, span)
+            | AstNode::Literal(_, span)
+            | AstNode::Error(span) => span,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum AstPattern {
-    Literal,
-    Id(String),
-    Wildcard,
-    Var(String),
-    Pair(Box<AstPattern>, Box<AstPattern>),
+    Literal(LiteralValue, Span),
+    Id(String, Span),
+    Wildcard(Span),
+    Var(String, Span),
+    Pair(Box<AstPattern>, Box<AstPattern>, Span),
+    /// `()` or a tuple of three or more patterns.
+    Tuple(Vec<AstPattern>, Span),
+    /// `head :: tail`
+    Cons(Box<AstPattern>, Box<AstPattern>, Span),
+    Nil(Span),
+    /// `[p1, ..., pn]`, matching lists of exactly n elements.
+    List(Vec<AstPattern>, Span),
+    /// A datatype constructor, applied to a pattern unless it is nullary.
+    Constructor(String, Option<Box<AstPattern>>, Span),
+    /// `ref p`, matching a cell whose contents match `p`.
+    Ref(Box<AstPattern>, Span),
+    /// `{label = p, ...}`. The flag is set if the fields end in `...`, so
+    /// that records with more fields match too.
+    Record(Vec<(String, AstPattern)>, bool, Span),
+}
+
+impl AstPattern {
+    pub fn span(&self) -> &Span {
+        match self {
+            AstPattern::Literal(_, span)
+            | AstPattern::Id(_, span)
+            | AstPattern::Wildcard(span)
+            | AstPattern::Var(_, span)
+            | AstPattern::Pair(_, _, span)
+            | AstPattern::Tuple(_, span)
+            | AstPattern::Cons(_, _, span)
+            | AstPattern::Nil(span)
+            | AstPattern::List(_, span)
+            | AstPattern::Constructor(_, _, span)
+            | AstPattern::Ref(_, span)
+            | AstPattern::Record(_, _, span) => span,
+        }
+    }
+}
+
+/// One function of a binding group, with the same parts as a `FunDecl`.
+#[derive(Debug, Clone)]
+pub struct FunBinding {
+    pub name: String,
+    pub clauses: Vec<(AstPattern, Box<AstNode>)>,
+    pub typ: Option<TypeAnnotation>,
+    pub span: Span,
 }
 
+/// One alternative of a datatype, such as `Node of 'a tree * 'a * 'a tree`.
 #[derive(Debug, Clone)]
+pub struct ConstructorDecl {
+    pub name: String,
+    pub arg: Option<Type>,
+    pub span: Span,
+}
+
+/// A type written in the source, such as the `int` in `val x : int = 1`.
+#[derive(Debug, Clone)]
+pub struct TypeAnnotation {
+    pub ty: Type,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum Type {
+    #[default]
+    Unit,
     Int,
+    Real,
     Bool,
     Char,
     String,
     Var(String),
     Arrow(Box<Type>, Box<Type>),
-    Product(Box<Type>, Box<Type>),
+    Product(Vec<Type>),
+    /// A type constructor applied to its arguments, e.g. `int list`.
+    Con(String, Vec<Type>),
+    /// `{label: t, ...}` with the fields sorted by label. A record labelled
+    /// `1` to `n` is the product of its fields, so build them with
+    /// `Type::record`.
+    Record(Vec<(String, Type)>),
 }
 
+impl Type {
+    /// The type of a record with `fields`, which is `unit` if there are none
+    /// and a product if the labels are those of a tuple.
+    pub fn record(mut fields: Vec<(String, Type)>) -> Type {
+        fields.sort_by(|(a, _), (b, _)| compare_labels(a, b));
+        if fields.is_empty() {
+            Type::Unit
+        } else if is_tuple_labels(fields.iter().map(|(label, _)| label.as_str())) {
+            Type::Product(fields.into_iter().map(|(_, ty)| ty).collect())
+        } else {
+            Type::Record(fields)
+        }
+    }
+
+    /// Wrap the type in parens when it appears as an operand of `*`, `->` or
+    /// a postfix type constructor.
+    fn fmt_operand(&self, f: &mut fmt::Formatter, wrap_product: bool) -> fmt::Result {
+        match self {
+            Type::Arrow(..) => write!(f, "({})", self),
+            Type::Product(_) if wrap_product => write!(f, "({})", self),
+            _ => write!(f, "{}", self),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Unit => write!(f, "unit"),
+            Type::Int => write!(f, "int"),
+            Type::Real => write!(f, "real"),
+            Type::Bool => write!(f, "bool"),
+            Type::Char => write!(f, "char"),
+            Type::String => write!(f, "string"),
+            Type::Var(name) => write!(f, "'{}", name),
+            Type::Arrow(param, ret) => {
+                param.fmt_operand(f, false)?;
+                write!(f, " -> {}", ret)
+            },
+            Type::Product(types) => {
+                for (i, t) in types.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " * ")?;
+                    }
+                    t.fmt_operand(f, true)?;
+                }
+                Ok(())
+            },
+            Type::Con(name, args) => {
+                match args.as_slice() {
+                    [] => {},
+                    [arg] => {
+                        arg.fmt_operand(f, true)?;
+                        write!(f, " ")?;
+                    },
+                    _ => {
+                        write!(f, "(")?;
+                        for (i, arg) in args.iter().enumerate() {
+                            if i > 0 {
+                                write!(f, ", ")?;
+                            }
+                            write!(f, "{}", arg)?;
+                        }
+                        write!(f, ") ")?;
+                    }
+                }
+                write!(f, "{}", name)
+            },
+            Type::Record(fields) => {
+                write!(f, "{{")?;
+                for (i, (label, ty)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", label, ty)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+/// The order of record labels: numeric labels by value, before the
+/// alphanumeric ones in alphabetical order.
+pub fn compare_labels(a: &str, b: &str) -> Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(x), Ok(y)) => x.cmp(&y),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+/// Whether sorted record labels are `1` to `n` for some `n` of at least 2,
+/// which makes the record a tuple.
+pub fn is_tuple_labels<'a>(labels: impl ExactSizeIterator<Item = &'a str>) -> bool {
+    labels.len() >= 2 && labels.enumerate().all(|(i, label)| label == (i + 1).to_string())
+}
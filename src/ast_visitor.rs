@@ -1,84 +1,138 @@
-use crate::ast::{AstNode, AstPattern, BinOp, LiteralValue, Type};
+use crate::ast::{AstNode, AstPattern, BinOp, ConstructorDecl, Fixity, FunBinding, LiteralValue, Type, TypeAnnotation};
+use crate::span::Span;
+use crate::diagnostic::Diagnostic;
 
 pub trait Visitable<T: Default> {
-    
-    fn visit(&mut self, ast: &AstNode) -> Result<T, String> {
-        self.visit_node(ast);
-        Ok(T::default())
-    } 
-    
-    fn visit_node(&mut self, node: &AstNode) -> Result<T, String> {
+
+    fn visit(&mut self, ast: &AstNode) -> Result<T, Diagnostic> {
+        self.visit_node(ast)
+    }
+
+    fn visit_node(&mut self, node: &AstNode) -> Result<T, Diagnostic> {
         match node {
-            AstNode::Program(stmts) => self.visit_program(stmts),
-            AstNode::ValDecl { pat, typ, exp } => self.visit_val_decl(pat, typ, exp),
-            AstNode::FunDecl { name, clauses, typ } => self.visit_fun_decl(name, clauses, typ),
-            AstNode::If { cond, then, else_ } => self.visit_if(cond, then, else_),
-            AstNode::Let { decl, body } => self.visit_let(decl, body),
-            AstNode::Fn { clauses } => self.visit_fn(clauses),
-            AstNode::BinOp { left, op, right } => self.visit_bin_op(left, op, right),
-            AstNode::App { func, arg } => self.visit_app(func, arg),
-            AstNode::Id(name) => self.visit_id(name),
-            AstNode::Var(name) => self.visit_var(name),
-            AstNode::Tuple(elements) => self.visit_tuple(elements),
-            AstNode::List(elements) => self.visit_list(elements),
-            AstNode::Literal(lit) => self.visit_literal(lit),
-        }
-    }
-    
-    fn visit_program(&mut self, stmts: &Vec<Box<AstNode>>) -> Result<T, String> {
+            AstNode::Program(stmts, span) => self.visit_program(stmts, span),
+            AstNode::ValDecl { pat, typ, exp, span } => self.visit_val_decl(pat, typ, exp, span),
+            AstNode::FunDecl { name, clauses, typ, span } => self.visit_fun_decl(name, clauses, typ, span),
+            AstNode::DatatypeDecl { name, params, constructors, span } => {
+                self.visit_datatype_decl(name, params, constructors, span)
+            },
+            AstNode::FixityDecl { names, fixity, span } => self.visit_fixity_decl(names, fixity, span),
+            AstNode::FunGroup { funs, span } => self.visit_fun_group(funs, span),
+            AstNode::Local { private, public, span } => self.visit_local(private, public, span),
+            AstNode::ExceptionDecl { name, arg, span } => self.visit_exception_decl(name, arg, span),
+            AstNode::If { cond, then, else_, span } => self.visit_if(cond, then, else_, span),
+            AstNode::Let { decls, body, span } => self.visit_let(decls, body, span),
+            AstNode::Fn { clauses, span } => self.visit_fn(clauses, span),
+            AstNode::Case { scrutinee, arms, span } => self.visit_case(scrutinee, arms, span),
+            AstNode::BinOp { left, op, right, span } => self.visit_bin_op(left, op, right, span),
+            AstNode::Neg { expr, span } => self.visit_neg(expr, span),
+            AstNode::AndAlso { left, right, span } => self.visit_andalso(left, right, span),
+            AstNode::OrElse { left, right, span } => self.visit_orelse(left, right, span),
+            AstNode::App { func, arg, span } => self.visit_app(func, arg, span),
+            AstNode::Raise { expr, span } => self.visit_raise(expr, span),
+            AstNode::Ref { expr, span } => self.visit_ref(expr, span),
+            AstNode::Deref { expr, span } => self.visit_deref(expr, span),
+            AstNode::Assign { target, value, span } => self.visit_assign(target, value, span),
+            AstNode::Handle { expr, arms, span } => self.visit_handle(expr, arms, span),
+            AstNode::Record { fields, span } => self.visit_record(fields, span),
+            AstNode::Selector { label, span } => self.visit_selector(label, span),
+            AstNode::Id(name, span) => self.visit_id(name, span),
+            AstNode::Var(name, span) => self.visit_var(name, span),
+            AstNode::Tuple(elements, span) => self.visit_tuple(elements, span),
+            AstNode::List(elements, span) => self.visit_list(elements, span),
+            AstNode::Seq(exprs, span) => self.visit_seq(exprs, span),
+            AstNode::Literal(lit, span) => self.visit_literal(lit, span),
+            AstNode::Error(span) => self.visit_error(span),
+        }
+    }
+
+    fn visit_program(&mut self, stmts: &Vec<Box<AstNode>>, _span: &Span) -> Result<T, Diagnostic> {
         for stmt in stmts {
             self.visit_node(stmt)?;
         }
         Ok(T::default())
     }
-    
+
     fn visit_fun_decl(
         &mut self, _name: &str,
         clauses: &Vec<(AstPattern, Box<AstNode>)>,
-        typ: &Option<Type>) -> Result<T, String>
-    {    
+        typ: &Option<TypeAnnotation>,
+        _span: &Span) -> Result<T, Diagnostic>
+    {
         for (pattern, expr) in clauses {
             self.visit_pattern(pattern)?;
             self.visit_node(expr)?;
         }
-        
-        if let Some(t) = typ {
-            self.visit_type(&Some(t.clone()))?;
+
+        if typ.is_some() {
+            self.visit_type(typ)?;
         }
-        
+
         Ok(T::default())
     }
-     
-    fn visit_val_decl(&mut self, pat: &AstPattern, typ: &Option<Type>, exp: &AstNode)
-        -> Result<T, String>
-    {    
-        self.visit_pattern(pat)?; 
-        if let Some(t) = typ {
-            self.visit_type(&Some(t.clone()))?;
-        } 
+
+    fn visit_fun_group(&mut self, funs: &[FunBinding], _span: &Span) -> Result<T, Diagnostic> {
+        for fun in funs {
+            self.visit_fun_decl(&fun.name, &fun.clauses, &fun.typ, &fun.span)?;
+        }
+        Ok(T::default())
+    }
+
+    fn visit_datatype_decl(&mut self, _name: &str, _params: &[String], _constructors: &[ConstructorDecl],
+                           _span: &Span) -> Result<T, Diagnostic>
+    {
+        Ok(T::default())
+    }
+
+    fn visit_exception_decl(&mut self, _name: &str, _arg: &Option<Type>, _span: &Span) -> Result<T, Diagnostic> {
+        Ok(T::default())
+    }
+
+    fn visit_fixity_decl(&mut self, _names: &[String], _fixity: &Fixity, _span: &Span) -> Result<T, Diagnostic> {
+        Ok(T::default())
+    }
+
+    fn visit_val_decl(&mut self, pat: &AstPattern, typ: &Option<TypeAnnotation>, exp: &AstNode,
+                      _span: &Span) -> Result<T, Diagnostic>
+    {
+        self.visit_pattern(pat)?;
+        if typ.is_some() {
+            self.visit_type(typ)?;
+        }
         self.visit_node(exp)?;
-        
+
         Ok(T::default())
     }
-    
-    fn visit_if(&mut self, cond: &AstNode, then: &AstNode, else_: &AstNode)
-        -> Result<T, String>
-    {    
+
+    fn visit_if(&mut self, cond: &AstNode, then: &AstNode, else_: &AstNode, _span: &Span)
+        -> Result<T, Diagnostic>
+    {
         self.visit_node(cond)?;
         self.visit_node(then)?;
         self.visit_node(else_)?;
-        
+
         Ok(T::default())
     }
-    
-    fn visit_let(&mut self, decl: &AstNode, body: &AstNode) -> Result<T, String> {
-        self.visit_node(decl)?;
+
+    fn visit_let(&mut self, decls: &[Box<AstNode>], body: &AstNode, _span: &Span) -> Result<T, Diagnostic> {
+        for decl in decls {
+            self.visit_node(decl)?;
+        }
         self.visit_node(body)?;
         Ok(T::default())
     }
-    
-    fn visit_fn(&mut self, clauses: &Vec<(AstPattern, Box<AstNode>)>)
-        -> Result<T, String>
+
+    fn visit_local(&mut self, private: &[Box<AstNode>], public: &[Box<AstNode>], _span: &Span)
+        -> Result<T, Diagnostic>
+    {
+        for decl in private.iter().chain(public) {
+            self.visit_node(decl)?;
+        }
+        Ok(T::default())
+    }
+
+    fn visit_fn(&mut self, clauses: &Vec<(AstPattern, Box<AstNode>)>, _span: &Span)
+        -> Result<T, Diagnostic>
     {
         for (pattern, expr) in clauses {
             self.visit_pattern(pattern)?;
@@ -86,81 +140,220 @@ pub trait Visitable<T: Default> {
         }
         Ok(T::default())
     }
-    
-    fn visit_bin_op(&mut self, left: &AstNode, op: &BinOp, right: &AstNode)
-        -> Result<T, String> {
+
+    fn visit_case(&mut self, scrutinee: &AstNode, arms: &Vec<(AstPattern, Box<AstNode>)>, _span: &Span)
+        -> Result<T, Diagnostic>
+    {
+        self.visit_node(scrutinee)?;
+        for (pattern, expr) in arms {
+            self.visit_pattern(pattern)?;
+            self.visit_node(expr)?;
+        }
+        Ok(T::default())
+    }
+
+    fn visit_bin_op(&mut self, left: &AstNode, _op: &BinOp, right: &AstNode, _span: &Span)
+        -> Result<T, Diagnostic> {
+        self.visit_node(left)?;
+        self.visit_node(right)?;
+        Ok(T::default())
+    }
+
+    fn visit_raise(&mut self, expr: &AstNode, _span: &Span) -> Result<T, Diagnostic> {
+        self.visit_node(expr)?;
+        Ok(T::default())
+    }
+
+    fn visit_ref(&mut self, expr: &AstNode, _span: &Span) -> Result<T, Diagnostic> {
+        self.visit_node(expr)?;
+        Ok(T::default())
+    }
+
+    fn visit_deref(&mut self, expr: &AstNode, _span: &Span) -> Result<T, Diagnostic> {
+        self.visit_node(expr)?;
+        Ok(T::default())
+    }
+
+    fn visit_assign(&mut self, target: &AstNode, value: &AstNode, _span: &Span) -> Result<T, Diagnostic> {
+        self.visit_node(target)?;
+        self.visit_node(value)?;
+        Ok(T::default())
+    }
+
+    fn visit_handle(&mut self, expr: &AstNode, arms: &Vec<(AstPattern, Box<AstNode>)>, _span: &Span)
+        -> Result<T, Diagnostic>
+    {
+        self.visit_node(expr)?;
+        for (pattern, body) in arms {
+            self.visit_pattern(pattern)?;
+            self.visit_node(body)?;
+        }
+        Ok(T::default())
+    }
+
+    fn visit_neg(&mut self, expr: &AstNode, _span: &Span) -> Result<T, Diagnostic> {
+        self.visit_node(expr)?;
+        Ok(T::default())
+    }
+
+    fn visit_andalso(&mut self, left: &AstNode, right: &AstNode, _span: &Span) -> Result<T, Diagnostic> {
+        self.visit_node(left)?;
+        self.visit_node(right)?;
+        Ok(T::default())
+    }
+
+    fn visit_orelse(&mut self, left: &AstNode, right: &AstNode, _span: &Span) -> Result<T, Diagnostic> {
         self.visit_node(left)?;
         self.visit_node(right)?;
         Ok(T::default())
     }
-    
-    fn visit_app(&mut self, func: &AstNode, arg: &AstNode)
-        -> Result<T, String> {
+
+    fn visit_app(&mut self, func: &AstNode, arg: &AstNode, _span: &Span)
+        -> Result<T, Diagnostic> {
         self.visit_node(func)?;
         self.visit_node(arg)?;
         Ok(T::default())
     }
-    
-    fn visit_id(&mut self, _name: &str) -> Result<T, String> {
+
+    fn visit_id(&mut self, _name: &str, _span: &Span) -> Result<T, Diagnostic> {
         Ok(T::default())
     }
-    
-    fn visit_var(&mut self, _name: &str) -> Result<T, String> {
+
+    fn visit_var(&mut self, _name: &str, _span: &Span) -> Result<T, Diagnostic> {
         Ok(T::default())
     }
-    
-    fn visit_tuple(&mut self, elements: &Vec<Box<AstNode>>) -> Result<T, String> {
+
+    fn visit_tuple(&mut self, elements: &Vec<Box<AstNode>>, _span: &Span) -> Result<T, Diagnostic> {
         for element in elements {
             self.visit_node(element)?;
         }
         Ok(T::default())
     }
-    
-    fn visit_list(&mut self, elements: &Vec<Box<AstNode>>) -> Result<T, String> {
+
+    fn visit_list(&mut self, elements: &Vec<Box<AstNode>>, _span: &Span) -> Result<T, Diagnostic> {
         for element in elements {
             self.visit_node(element)?;
         }
         Ok(T::default())
     }
-    
-    fn visit_pattern(&mut self, pat: &AstPattern) -> Result<T, String> {
+
+    fn visit_seq(&mut self, exprs: &[Box<AstNode>], _span: &Span) -> Result<T, Diagnostic> {
+        for expr in exprs {
+            self.visit_node(expr)?;
+        }
+        Ok(T::default())
+    }
+
+    fn visit_record(&mut self, fields: &[(String, Box<AstNode>)], _span: &Span) -> Result<T, Diagnostic> {
+        for (_, expr) in fields {
+            self.visit_node(expr)?;
+        }
+        Ok(T::default())
+    }
+
+    fn visit_selector(&mut self, _label: &str, _span: &Span) -> Result<T, Diagnostic> {
+        Ok(T::default())
+    }
+
+    fn visit_pattern(&mut self, pat: &AstPattern) -> Result<T, Diagnostic> {
         match pat {
-            AstPattern::Literal => self.visit_literal_pattern(),
-            AstPattern::Id(name) => self.visit_id_pattern(name),
-            AstPattern::Wildcard => self.visit_wildcard_pattern(),
-            AstPattern::Var(name) => self.visit_var_pattern(name),
-            AstPattern::Pair(first, second) => self.visit_pair_pattern(first, second),
+            AstPattern::Literal(lit, span) => self.visit_literal_pattern(lit, span),
+            AstPattern::Id(name, span) => self.visit_id_pattern(name, span),
+            AstPattern::Wildcard(span) => self.visit_wildcard_pattern(span),
+            AstPattern::Var(name, span) => self.visit_var_pattern(name, span),
+            AstPattern::Pair(first, second, span) => self.visit_pair_pattern(first, second, span),
+            AstPattern::Tuple(elements, span) => self.visit_tuple_pattern(elements, span),
+            AstPattern::Cons(head, tail, span) => self.visit_cons_pattern(head, tail, span),
+            AstPattern::Nil(span) => self.visit_nil_pattern(span),
+            AstPattern::List(elements, span) => self.visit_list_pattern(elements, span),
+            AstPattern::Constructor(name, arg, span) => self.visit_constructor_pattern(name, arg, span),
+            AstPattern::Ref(inner, span) => self.visit_ref_pattern(inner, span),
+            AstPattern::Record(fields, flexible, span) => self.visit_record_pattern(fields, *flexible, span),
         }
     }
-    
-    fn visit_literal_pattern(&mut self) -> Result<T, String> {
+
+    fn visit_literal_pattern(&mut self, _lit: &LiteralValue, _span: &Span) -> Result<T, Diagnostic> {
         Ok(T::default())
     }
-    
-    fn visit_id_pattern(&mut self, _name: &str) -> Result<T, String> {
+
+    fn visit_id_pattern(&mut self, _name: &str, _span: &Span) -> Result<T, Diagnostic> {
         Ok(T::default())
     }
-    
-    fn visit_wildcard_pattern(&mut self) -> Result<T, String> {
+
+    fn visit_wildcard_pattern(&mut self, _span: &Span) -> Result<T, Diagnostic> {
         Ok(T::default())
     }
-    
-    fn visit_var_pattern(&mut self, _name: &str) -> Result<T, String> {
+
+    fn visit_var_pattern(&mut self, _name: &str, _span: &Span) -> Result<T, Diagnostic> {
         Ok(T::default())
     }
-    
-    fn visit_pair_pattern(&mut self, first: &AstPattern, second: &AstPattern) -> Result<T, String> {
+
+    fn visit_pair_pattern(&mut self, first: &AstPattern, second: &AstPattern, _span: &Span)
+        -> Result<T, Diagnostic>
+    {
         self.visit_pattern(first)?;
         self.visit_pattern(second)?;
         Ok(T::default())
     }
-    
-    fn visit_type(&mut self, _typ: &Option<Type>) -> Result<T, String> {
+
+    fn visit_tuple_pattern(&mut self, elements: &Vec<AstPattern>, _span: &Span) -> Result<T, Diagnostic> {
+        for element in elements {
+            self.visit_pattern(element)?;
+        }
+        Ok(T::default())
+    }
+
+    fn visit_cons_pattern(&mut self, head: &AstPattern, tail: &AstPattern, _span: &Span)
+        -> Result<T, Diagnostic>
+    {
+        self.visit_pattern(head)?;
+        self.visit_pattern(tail)?;
         Ok(T::default())
     }
-    
-    fn visit_literal(&mut self, _lit_node: &LiteralValue) -> Result<T, String> {
+
+    fn visit_nil_pattern(&mut self, _span: &Span) -> Result<T, Diagnostic> {
+        Ok(T::default())
+    }
+
+    fn visit_list_pattern(&mut self, elements: &Vec<AstPattern>, _span: &Span) -> Result<T, Diagnostic> {
+        for element in elements {
+            self.visit_pattern(element)?;
+        }
+        Ok(T::default())
+    }
+
+    fn visit_constructor_pattern(&mut self, _name: &str, arg: &Option<Box<AstPattern>>, _span: &Span)
+        -> Result<T, Diagnostic>
+    {
+        if let Some(arg) = arg {
+            self.visit_pattern(arg)?;
+        }
         Ok(T::default())
     }
-}
 
+    fn visit_ref_pattern(&mut self, inner: &AstPattern, _span: &Span) -> Result<T, Diagnostic> {
+        self.visit_pattern(inner)?;
+        Ok(T::default())
+    }
+
+    fn visit_record_pattern(&mut self, fields: &[(String, AstPattern)], _flexible: bool, _span: &Span)
+        -> Result<T, Diagnostic>
+    {
+        for (_, pat) in fields {
+            self.visit_pattern(pat)?;
+        }
+        Ok(T::default())
+    }
+
+    fn visit_type(&mut self, _typ: &Option<TypeAnnotation>) -> Result<T, Diagnostic> {
+        Ok(T::default())
+    }
+
+    fn visit_literal(&mut self, _lit_node: &LiteralValue, _span: &Span) -> Result<T, Diagnostic> {
+        Ok(T::default())
+    }
+
+    fn visit_error(&mut self, _span: &Span) -> Result<T, Diagnostic> {
+        Ok(T::default())
+    }
+}
@@ -0,0 +1,174 @@
+use std::fmt;
+use crate::span::{SourceMap, Span};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// A span of source with a message attached. The primary label marks where
+/// the problem is, secondary labels point at related code.
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub msg: String,
+    pub primary: bool,
+}
+
+/// A message reported to the user by any phase of the pipeline.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub msg: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, msg: impl Into<String>) -> Self {
+        Diagnostic {
+            severity,
+            msg: msg.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: Vec::new(),
+        }
+    }
+
+    pub fn error(msg: impl Into<String>) -> Self {
+        Self::new(Severity::Error, msg)
+    }
+
+    pub fn warning(msg: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, msg)
+    }
+
+    pub fn with_primary(mut self, span: Span, msg: impl Into<String>) -> Self {
+        self.labels.push(Label { span, msg: msg.into(), primary: true });
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, msg: impl Into<String>) -> Self {
+        self.labels.push(Label { span, msg: msg.into(), primary: false });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    pub fn primary_span(&self) -> Option<Span> {
+        self.labels.iter().find(|l| l.primary).map(|l| l.span)
+    }
+
+    /// Render the diagnostic rustc-style, quoting each labelled source line
+    /// with carets under the primary span and dashes under secondary ones.
+    pub fn render(&self, files: &SourceMap) -> String {
+        let mut out = format!("{}: {}\n", self.severity, self.msg);
+
+        let mut labels: Vec<&Label> = self.labels.iter().collect();
+        labels.sort_by_key(|l| (l.span.file, l.span.line, !l.primary, l.span.col));
+
+        let gutter = labels
+            .iter()
+            .map(|l| l.span.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let pad = " ".repeat(gutter);
+
+        if let Some(span) = self.primary_span().or(labels.first().map(|l| l.span)) {
+            let name = files.name(span.file).unwrap_or("<unknown>");
+            out += &format!("{}--> {}:{}:{}\n", pad, name, span.line, span.col);
+        }
+
+        let mut last_line: Option<(usize, usize)> = None;
+        for label in &labels {
+            let span = label.span;
+            let Some(source) = files.source(span.file) else {
+                continue;
+            };
+            let (line_start, line_text) = line_at(source, span.start);
+            let key = (span.file, span.line);
+            if last_line != Some(key) {
+                match last_line {
+                    Some((file, line)) if file == span.file && span.line > line + 1 => {
+                        out += "...\n";
+                    },
+                    None => out += &format!("{} |\n", pad),
+                    _ => {},
+                }
+                out += &format!("{:>width$} | {}\n", span.line, line_text, width = gutter);
+                last_line = Some(key);
+            }
+
+            let offset = span.start.saturating_sub(line_start).min(line_text.len());
+            let indent: String = line_text[..offset]
+                .chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            // multi-line spans are underlined up to the end of their first line
+            let end = span.end.min(line_start + line_text.len()).max(span.start);
+            let width = line_text
+                .get(offset..end - line_start)
+                .map(|s| s.chars().count())
+                .unwrap_or(0)
+                .max(1);
+            let marker = if label.primary { "^" } else { "-" };
+            let mut line = format!("{} | {}{}", pad, indent, marker.repeat(width));
+            if !label.msg.is_empty() {
+                line += &format!(" {}", label.msg);
+            }
+            out += line.trim_end();
+            out += "\n";
+        }
+
+        for note in &self.notes {
+            out += &format!("{} = note: {}\n", pad, note);
+        }
+        for help in &self.help {
+            out += &format!("{} = help: {}\n", pad, help);
+        }
+        out
+    }
+}
+
+/// The byte offset where the line containing `pos` starts, and the text of
+/// that line without its newline.
+fn line_at(source: &str, pos: usize) -> (usize, &str) {
+    let pos = pos.min(source.len());
+    let start = source[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let end = source[start..].find('\n').map(|i| start + i).unwrap_or(source.len());
+    (start, source[start..end].trim_end_matches('\r'))
+}
+
+/// Without the source at hand a diagnostic is shown on one line, prefixed
+/// with the location of its primary label.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.primary_span() {
+            Some(span) => write!(f, "{}: {}: {}", span, self.severity, self.msg),
+            None => write!(f, "{}: {}", self.severity, self.msg),
+        }
+    }
+}
+
+impl std::error::Error for Diagnostic {}
@@ -0,0 +1,80 @@
+use std::fmt;
+use crate::span::Span;
+use crate::diagnostic::Diagnostic;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LexErrKind {
+    InvalidCharacter,
+    MalformedNumber,
+    InvalidEscape,
+    InvalidCharLiteral,
+    UnterminatedString,
+    UnterminatedComment,
+    UnmatchedCommentClose,
+}
+
+impl fmt::Display for LexErrKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LexErrKind::InvalidCharacter => write!(f, "Invalid character"),
+            LexErrKind::MalformedNumber => write!(f, "Malformed number"),
+            LexErrKind::InvalidEscape => write!(f, "Invalid escape sequence"),
+            LexErrKind::InvalidCharLiteral => write!(f, "Invalid character literal"),
+            LexErrKind::UnterminatedString => write!(f, "Unterminated string"),
+            LexErrKind::UnterminatedComment => write!(f, "Unterminated comment"),
+            LexErrKind::UnmatchedCommentClose => write!(f, "Unmatched comment close"),
+        }
+    }
+}
+
+impl LexErrKind {
+    /// Text for the label under the offending source.
+    fn label(&self) -> &'static str {
+        match self {
+            LexErrKind::InvalidCharacter => "not valid in tinyml source",
+            LexErrKind::MalformedNumber => "not a valid number",
+            LexErrKind::InvalidEscape => "unknown escape",
+            LexErrKind::InvalidCharLiteral => "expected a single character",
+            LexErrKind::UnterminatedString => "literal starts here",
+            LexErrKind::UnterminatedComment => "comment starts here",
+            LexErrKind::UnmatchedCommentClose => "no comment to close",
+        }
+    }
+}
+
+/// A problem found by the lexer. Lexing carries on after one, so a single
+/// run reports every error in the file.
+#[derive(Debug, Clone)]
+pub struct LexError {
+    pub kind: LexErrKind,
+    pub msg: String,
+    pub span: Span,
+}
+
+impl LexError {
+    pub fn new(kind: LexErrKind, msg: impl Into<String>, span: Span) -> Self {
+        Self { kind, msg: msg.into(), span }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LexError: {} - {} (line {}, column {})",
+               self.kind, self.msg, self.span.line, self.span.col)
+    }
+}
+
+impl std::error::Error for LexError {}
+
+impl From<LexError> for Diagnostic {
+    fn from(err: LexError) -> Self {
+        let diagnostic = Diagnostic::error(err.msg).with_primary(err.span, err.kind.label());
+        match err.kind {
+            LexErrKind::UnterminatedString => {
+                diagnostic.with_help("add a closing '\"' before the end of the line")
+            },
+            LexErrKind::UnterminatedComment => diagnostic.with_help("every '(*' needs a matching '*)'"),
+            _ => diagnostic,
+        }
+    }
+}
@@ -1,10 +1,7 @@
-use std::{boxed, fs::File};
-use regex::Regex;
 use std::collections::HashMap;
-use std::path::Path;
-use std::str::FromStr;
-use lazy_static::lazy_static;
 use strum::IntoEnumIterator;
+use crate::span::Span;
+use crate::lex_error::{LexError, LexErrKind};
 use strum_macros::{EnumIter, EnumString, Display};
 
 #[derive(Eq, PartialEq, Debug, EnumIter, EnumString, Display, Clone)]
@@ -14,6 +11,8 @@ pub enum TokenType {
     Let,
     #[strum(to_string = "fun")]
     Fun,
+    #[strum(to_string = "fn")]
+    Fn,
     #[strum(to_string = "in")]
     In,
     #[strum(to_string = "end")]
@@ -26,6 +25,8 @@ pub enum TokenType {
     Val,
     #[strum(to_string = "type")]
     Type,
+    #[strum(to_string = "datatype")]
+    Datatype,
     #[strum(to_string = "nil")]
     Nil,
     #[strum(to_string = "none")]
@@ -38,6 +39,8 @@ pub enum TokenType {
     TypeBool, 
     #[strum(to_string = "char")]
     TypeChar,
+    #[strum(to_string = "real")]
+    TypeReal,
     #[strum(to_string = "string")]
     TypeString,
     #[strum(to_string = "if")]
@@ -46,6 +49,32 @@ pub enum TokenType {
     Then,
     #[strum(to_string = "else")]
     Else,
+    #[strum(to_string = "andalso")]
+    AndAlso,
+    #[strum(to_string = "orelse")]
+    OrElse,
+    #[strum(to_string = "infix")]
+    Infix,
+    #[strum(to_string = "infixl")]
+    Infixl,
+    #[strum(to_string = "infixr")]
+    Infixr,
+    #[strum(to_string = "op")]
+    Op,
+    #[strum(to_string = "and")]
+    And,
+    #[strum(to_string = "rec")]
+    Rec,
+    #[strum(to_string = "exception")]
+    Exception,
+    #[strum(to_string = "raise")]
+    Raise,
+    #[strum(to_string = "handle")]
+    Handle,
+    #[strum(to_string = "ref")]
+    Ref,
+    #[strum(to_string = "local")]
+    Local,
 
     // Syntactic elements
     Comment,
@@ -63,6 +92,15 @@ pub enum TokenType {
     LeftParen,
     #[strum(to_string = ")")]
     RightParen,
+    #[strum(to_string = "{")]
+    LeftBrace,
+    /// '}', which strum would read as the end of a format argument, so it
+    /// is added to the token map by hand.
+    RightBrace,
+    #[strum(to_string = "#")]
+    Hash,
+    #[strum(to_string = "...")]
+    Ellipsis,
     #[strum(to_string = "=")]
     Equal,
     #[strum(to_string = "=>")]
@@ -91,23 +129,25 @@ pub enum TokenType {
     Multiply,
     #[strum(to_string = "/")]
     Divide,
+    #[strum(to_string = "~")]
+    Tilde,
     #[strum(to_string = "<")]
-    StrictLess,
-    #[strum(to_string = "<=")]
     Less,
+    #[strum(to_string = "<=")]
+    LessEqual,
     #[strum(to_string = ">")]
-    StrictGreater,
-    #[strum(to_string = ">=")]
     Greater,
-    #[strum(to_string = "==")]
-    CompEqual,
-    #[strum(to_string = "!=")]
-    CompNotEqual,
+    #[strum(to_string = ">=")]
+    GreaterEqual,
+    #[strum(to_string = "<>")]
+    NotEqual,
     
     Id(String),
     Bool(String),
     Integer(String),
+    /// The decoded contents of a string or char literal.
     String(String),
+    Char(String),
     Float(String),
     EOF,
     Error,
@@ -118,40 +158,56 @@ pub struct Token {
     pub line: usize,
     pub col: usize,
     pub len: usize,
+    /// byte offset of the token in the source, and the file it came from.
+    pub start: usize,
+    pub file: usize,
     pub ty: TokenType,
     pub next: Option<Box<Token>>,
 }
 
 impl Token {
-    pub fn new(line: usize, col: usize, len: usize, ty: TokenType) -> Self {
-        return Token {
+    pub fn new(line: usize, col: usize, len: usize, start: usize, file: usize, ty: TokenType) -> Self {
+        Token {
             line,
             col,
             len,
+            start,
+            file,
             ty,
             next: None,
         } 
     }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.file, self.start, self.start + self.len, self.line, self.col)
+    }
 } 
 
 pub struct Lexer {
     cur_idx: usize,
     start_idx: usize,
     max_idx: usize,
+    /// nesting depth of the `(* ... *)` comment being skipped.
     comment_depth: usize,
     pos_line: usize,
     pos_col: usize,
+    file: usize,
     source: String,
+    errors: Vec<LexError>,
     pub token_dict: TokenDict
 }
 
-type SourceIterator<'a> = std::iter::Peekable<std::str::Chars<'a>>;
-
 pub struct TokenDict {
     pub map: HashMap<String, TokenType>,
     pub max_key_len: usize
 }
 
+impl Default for TokenDict {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TokenDict {
 
     pub fn new() -> Self {
@@ -164,8 +220,11 @@ impl TokenDict {
                 // handled specially.
                 TokenType::Id(_) | TokenType::Integer(_)
                                  | TokenType::Bool(_) 
+                                 | TokenType::String(_) 
+                                 | TokenType::Char(_) 
                                  | TokenType::Float(_) 
                                  | TokenType::Error
+                                 | TokenType::RightBrace
                                  | TokenType::EOF  => continue,
                 _ => {}
                              
@@ -177,6 +236,7 @@ impl TokenDict {
             map.insert(token_string, token_type);
         }
 
+        map.insert(String::from("}"), TokenType::RightBrace);
         map.insert(String::from("true"), TokenType::Bool(String::from("true")));
         map.insert(String::from("false"), TokenType::Bool(String::from("false")));
  
@@ -189,17 +249,19 @@ impl TokenDict {
 
 impl Lexer {
  
-    pub fn new(source: String) -> Self {
-        return Lexer {
+    pub fn new(source: String, file: usize) -> Self {
+        Lexer {
             cur_idx: 0,
             start_idx: 0,
             max_idx: source.len(),
             pos_line: 1,
             pos_col: 1,
+            file,
             comment_depth: 0,
             source,
+            errors: Vec::new(),
             token_dict: TokenDict::new()
-        };  
+        }  
     }
   
     pub fn match_id_or_kw(&self, chars: &str)
@@ -210,7 +272,7 @@ impl Lexer {
         for c in chars.chars() {
             if c.is_alphanumeric() || c == '_' {
                 id_or_kw.push(c);
-                len += 1;
+                len += c.len_utf8();
             } else {
                 break;
             }
@@ -225,33 +287,69 @@ impl Lexer {
         }
     }
 
+    /// Match a symbolic identifier such as '@' or '<+>', which is the longest
+    /// run of symbol characters. Runs that spell an operator or punctuation
+    /// token, like '=>' or '::', are that token instead.
+    pub fn match_symbolic(&self, chars: &str)
+        -> (Option<TokenType>, usize)
+    {
+        let len = chars.find(|c| !is_symbolic(c)).unwrap_or(chars.len());
+        if len == 0 {
+            return (None, 0);
+        }
+        let symbol = &chars[..len];
+        match self.token_dict.map.get(symbol) {
+            Some(token_ty) => (Some(token_ty.clone()), len),
+            None => (Some(TokenType::Id(symbol.to_string())), len),
+        }
+    }
+
     pub fn match_number(&self, chars: &str)
         -> (Option<TokenType>, usize)
     {
-        let mut len = 0;
-        let mut number = String::new();
-        let mut is_float = false;
+        // SML writes negative literals with a leading '~'
+        let (mut number, digits) = match chars.strip_prefix('~') {
+            Some(rest) => (String::from("~"), rest),
+            None => (String::new(), chars),
+        };
+        let mut len = number.len();
+        let mut dots = 0;
         
-        // Collect integer part
-        for c in chars.chars() {
-            if c.is_digit(10) {
+        // Collect digits, and any dots so '1.2.3' is rejected as a whole
+        for c in digits.chars() {
+            if c.is_ascii_digit() {
                 number.push(c);
                 len += 1;
-            } else if c == '.' && !is_float {
-                is_float = true;
-                number.push(c);
-                len += 1;
-            } else if is_float && c.is_digit(10) {
+            } else if c == '.' {
+                dots += 1;
                 number.push(c);
                 len += 1;
             } else {
                 break;
             }
         } 
-        if number.is_empty() {
+        if number.is_empty() || number == "~" {
             return (None, 0);
         } 
-        if is_float {
+        if dots > 1 || number.ends_with('.') {
+            return (Some(TokenType::Error), len);
+        }
+        
+        // An exponent such as 'e~3', which makes the number a real
+        let mut exponent = false;
+        let rest = &chars[len..];
+        if let Some(exp) = rest.strip_prefix(['e', 'E']) {
+            let sign = usize::from(exp.starts_with('~'));
+            let exp_digits = exp[sign..].chars().take_while(|c| c.is_ascii_digit()).count();
+            if exp_digits > 0 {
+                let exp_len = 1 + sign + exp_digits;
+                number.push_str(&rest[..exp_len]);
+                len += exp_len;
+                exponent = true;
+            }
+        }
+        
+        if dots == 1 || exponent {
             (Some(TokenType::Float(number)), len)
         } else {
             (Some(TokenType::Integer(number)), len)
@@ -261,6 +359,11 @@ impl Lexer {
     pub fn match_syntax(&self, chars: &str)
         -> (Option<TokenType>, usize)
     {    
+        // '...' is the only three-character token
+        if chars.starts_with("...") {
+            return (Some(TokenType::Ellipsis), 3);
+        }
+        
         // Try to match a two-character token first
         let mut chars_iter = chars.chars();
         let first = chars_iter.next();
@@ -284,68 +387,295 @@ impl Lexer {
         (None, 0)
     }
 
-    pub fn tokenize(&mut self) -> Vec<Token> {
+    /// Move past `text`, which starts at the current position, keeping the
+    /// line and column up to date.
+    fn advance(&mut self, text: &str) {
+        for ch in text.chars() {
+            self.cur_idx += ch.len_utf8();
+            if ch == '\n' {
+                self.pos_line += 1;
+                self.pos_col = 1;
+            } else {
+                self.pos_col += 1;
+            }
+        }
+    }
+
+    /// Lex a string literal, or a char literal if `prefix` is the length of
+    /// its leading '#'. Errors are reported and the literal is still
+    /// produced, so lexing carries on after it.
+    fn lex_text_literal(&mut self, prefix: usize) -> Token {
+        let remaining = &self.source[self.cur_idx..];
+        let scan = scan_string(&remaining[prefix..]);
+        let len = prefix + scan.len;
+        let text = remaining[..len].to_string();
+        let (line, col, start, file) = (self.pos_line, self.pos_col, self.cur_idx, self.file);
+        let span_at = |offset: usize, len: usize| {
+            let before = &text[..prefix + offset];
+            let line = line + before.matches('\n').count();
+            let col = match before.rfind('\n') {
+                Some(nl) => before[nl + 1..].chars().count() + 1,
+                None => col + before.chars().count(),
+            };
+            Span::new(file, start + prefix + offset, start + prefix + offset + len, line, col)
+        };
+
+        let kind = if prefix > 0 { "character" } else { "string" };
+        for (offset, len, msg) in &scan.errors {
+            self.errors.push(LexError::new(LexErrKind::InvalidEscape, msg.clone(), span_at(*offset, *len)));
+        }
+        if !scan.terminated {
+            let msg = format!("Unterminated {} literal", kind);
+            self.errors.push(LexError::new(LexErrKind::UnterminatedString, msg, span_at(0, 1)));
+        }
+        if prefix > 0 && scan.terminated && scan.value.chars().count() != 1 {
+            let span = Span::new(file, start, start + len, line, col);
+            let msg = "Character literal must contain exactly one character";
+            self.errors.push(LexError::new(LexErrKind::InvalidCharLiteral, msg, span));
+        }
+
+        let ty = if prefix > 0 {
+            TokenType::Char(scan.value)
+        } else {
+            TokenType::String(scan.value)
+        };
+        let tok = Token::new(line, col, len, start, self.file, ty);
+        self.advance(&text);
+        tok
+    }
+
+    /// Skip the comment starting at the current position, including any
+    /// comments nested inside it.
+    fn skip_comment(&mut self) {
+        let (line, col, start) = (self.pos_line, self.pos_col, self.cur_idx);
+        self.comment_depth = 0;
+        while self.cur_idx < self.max_idx {
+            let remaining = &self.source[self.cur_idx..];
+            if remaining.starts_with("(*") {
+                self.comment_depth += 1;
+                self.advance("(*");
+            } else if remaining.starts_with("*)") {
+                self.comment_depth -= 1;
+                self.advance("*)");
+                if self.comment_depth == 0 {
+                    return;
+                }
+            } else if let Some(ch) = remaining.chars().next() {
+                self.advance(&ch.to_string());
+            }
+        }
+        let span = Span::new(self.file, start, start + 2, line, col);
+        let msg = format!("Unterminated comment starting at line {}", line);
+        self.errors.push(LexError::new(LexErrKind::UnterminatedComment, msg, span));
+    }
+
+    /// Split the source into tokens. Lexing carries on past errors, so all
+    /// of them are returned together.
+    pub fn tokenize(&mut self) -> Result<Vec<Token>, Vec<LexError>> {
 
         let mut tokens: Vec<Token> = Vec::new();  
-        while self.cur_idx < self.source.len() {
+        while self.cur_idx < self.max_idx {
             self.start_idx = self.cur_idx;
             
             // Get the remaining part of the source
             let remaining = &self.source[self.cur_idx..];
+            let Some(ch) = remaining.chars().next() else {
+                break;
+            };
             
             // Skip whitespaces
-            if let Some(ch) = remaining.chars().next() {
-                if ch.is_whitespace() {
-                    self.cur_idx += 1;
-                    self.pos_col += 1; 
-                    if ch == '\n' {
-                        self.pos_line += 1;
-                        self.pos_col = 1;
-                    } 
-                    continue;
-                }
+            if ch.is_whitespace() {
+                self.advance(&ch.to_string());
+                continue;
+            }
+            
+            if remaining.starts_with("(*") {
+                self.skip_comment();
+                continue;
+            }
+            if remaining.starts_with("*)") {
+                let span = Span::new(self.file, self.cur_idx, self.cur_idx + 2, self.pos_line, self.pos_col);
+                let msg = "Unmatched '*)' outside of a comment";
+                self.errors.push(LexError::new(LexErrKind::UnmatchedCommentClose, msg, span));
+                self.advance("*)");
+                continue;
+            }
+            if remaining.starts_with('"') {
+                let tok = self.lex_text_literal(0);
+                tokens.push(tok);
+                continue;
+            }
+            if remaining.starts_with("#\"") {
+                let tok = self.lex_text_literal(1);
+                tokens.push(tok);
+                continue;
             }
             
             // Try to match tokens
-            let (token_type, token_len) = if let Some(ch) = remaining.chars().next() {
-                if ch.is_alphabetic() || ch == '_' {
-                    self.match_id_or_kw(remaining)
-                } else if ch.is_digit(10) {
-                    self.match_number(remaining)
-                } else {
-                    self.match_syntax(remaining)
+            let (token_type, token_len) = if ch.is_alphabetic() || ch == '_' {
+                self.match_id_or_kw(remaining)
+            } else if ch.is_ascii_digit() || remaining.starts_with('~') {
+                match self.match_number(remaining) {
+                    (None, _) => self.match_symbolic(remaining),
+                    number => number,
                 }
+            } else if is_symbolic(ch) {
+                self.match_symbolic(remaining)
             } else {
-                break;
+                self.match_syntax(remaining)
             };
             
-            if let Some(tt) = token_type {
-                let tok = Token {
-                    line: self.pos_line,
-                    col: self.pos_col,
-                    len: token_len,
-                    ty: tt,
-                    next: None
+            let token_len = if token_type.is_some() { token_len } else { ch.len_utf8() };
+            let text = remaining[..token_len].to_string();
+            let span = Span::new(self.file, self.cur_idx, self.cur_idx + token_len, self.pos_line, self.pos_col);
+            match token_type {
+                Some(TokenType::Error) => {
+                    let msg = format!("Malformed number '{}'", text);
+                    self.errors.push(LexError::new(LexErrKind::MalformedNumber, msg, span));
+                },
+                Some(tt) => {
+                    // Add token to the linked list
+                    tokens.push(Token::new(span.line, span.col, token_len, span.start, self.file, tt));
+                },
+                None => {
+                    let msg = format!("Invalid character '{}'", ch);
+                    self.errors.push(LexError::new(LexErrKind::InvalidCharacter, msg, span));
+                },
+            }
+            
+            // Update position
+            self.advance(&text);
+        }
+        
+        if self.errors.is_empty() {
+            Ok(tokens)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    } 
+}
+
+/// Characters that make up symbolic identifiers and operators.
+fn is_symbolic(ch: char) -> bool {
+    "!%&$#+-/:<=>?@\\~`^|*".contains(ch)
+}
+
+/// The result of scanning a string literal.
+struct StringScan {
+    /// contents with escapes decoded.
+    value: String,
+    /// byte length of the literal including its quotes.
+    len: usize,
+    terminated: bool,
+    /// byte offset and length of each bad escape, with a message.
+    errors: Vec<(usize, usize, String)>,
+}
+
+/// Scan the string literal at the start of `src`, which begins with '"'.
+/// The literal ends at the closing quote, or before the end of the line if
+/// it is unterminated; a gap `\ ... \` may span lines.
+fn scan_string(src: &str) -> StringScan {
+    let mut scan = StringScan { value: String::new(), len: src.len(), terminated: false, errors: Vec::new() };
+    let mut chars = src.char_indices().skip(1).peekable();
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            '"' => {
+                scan.len = i + 1;
+                scan.terminated = true;
+                return scan;
+            },
+            '\n' => {
+                scan.len = i;
+                return scan;
+            },
+            '\\' => {
+                let Some((j, esc)) = chars.next() else { break };
+                let decoded = match esc {
+                    'n' => Some('\n'),
+                    't' => Some('\t'),
+                    'a' => Some('\x07'),
+                    'b' => Some('\x08'),
+                    'v' => Some('\x0b'),
+                    'f' => Some('\x0c'),
+                    'r' => Some('\r'),
+                    '\\' => Some('\\'),
+                    '"' => Some('"'),
+                    '^' => chars
+                        .next_if(|(_, c)| ('@'..='_').contains(c))
+                        .map(|(_, c)| (c as u8 - b'@') as char),
+                    'u' => {
+                        let digits: String = (0..4).map_while(|_| chars.next_if(|(_, c)| c.is_ascii_hexdigit()))
+                            .map(|(_, c)| c)
+                            .collect();
+                        if digits.len() == 4 {
+                            u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32)
+                        } else {
+                            None
+                        }
+                    },
+                    d if d.is_ascii_digit() => {
+                        let rest: String = (0..2).map_while(|_| chars.next_if(|(_, c)| c.is_ascii_digit()))
+                            .map(|(_, c)| c)
+                            .collect();
+                        format!("{}{}", d, rest).parse::<u32>().ok()
+                            .filter(|n| rest.len() == 2 && *n <= 255)
+                            .and_then(char::from_u32)
+                    },
+                    w if w.is_whitespace() => {
+                        // A gap: whitespace up to the next backslash is ignored
+                        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+                        match chars.next_if(|(_, c)| *c == '\\') {
+                            Some(_) => continue,
+                            None => {
+                                let end = chars.peek().map(|(k, _)| *k).unwrap_or(src.len());
+                                scan.errors.push((i, end - i, "Unterminated gap in string literal".to_string()));
+                                continue;
+                            },
+                        }
+                    },
+                    _ => None,
                 };
-                
-                // Update position
-                self.cur_idx += token_len;
-                self.pos_col += token_len;
-                
-                // Add token to the linked list
-                tokens.push(tok); 
-            } else {
-                // Handle invalid character
-                if let Some(ch) = remaining.chars().next() {
-                    println!("Invalid character: {}", ch);
-                    self.cur_idx += 1;
-                    self.pos_col += 1;
-                } else {
-                    break;
+                let end = chars.peek().map(|(k, _)| *k).unwrap_or(src.len());
+                match decoded {
+                    Some(c) => scan.value.push(c),
+                    None => {
+                        let msg = format!("Invalid escape sequence '{}'", &src[i..end]);
+                        scan.errors.push((i, end.max(j + esc.len_utf8()) - i, msg));
+                    },
                 }
-            } 
+            },
+            c => scan.value.push(c),
         }
-        tokens
-    } 
+    }
+    scan
 }
 
+/// Escape `s` for printing inside an SML string or char literal, so that
+/// `scan_string` reads it back as `s`.
+pub fn escape_string(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\x07' => escaped.push_str("\\a"),
+            '\x08' => escaped.push_str("\\b"),
+            '\x0b' => escaped.push_str("\\v"),
+            '\x0c' => escaped.push_str("\\f"),
+            '\r' => escaped.push_str("\\r"),
+            '\0'..='\x1f' => {
+                escaped.push('\\');
+                escaped.push('^');
+                escaped.push((c as u8 + b'@') as char);
+            },
+            ' '..='~' => escaped.push(c),
+            '\x7f'..='\u{ff}' => escaped.push_str(&format!("\\{:03}", c as u32)),
+            '\u{100}'..='\u{ffff}' => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            // There is no escape for these, but they may appear as they are
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
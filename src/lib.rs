@@ -1,9 +1,13 @@
 pub mod lexer;
 pub mod ast;
+pub mod span;
 pub mod parser;
 pub mod util;
 pub mod symtab;
 pub mod ast_visitor;
 pub mod parse_error;
+pub mod lex_error;
+pub mod diagnostic;
 pub use parse_error::*;
 pub mod passes;
+pub mod value;
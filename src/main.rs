@@ -1,40 +1,82 @@
-
 use std::env;
 use std::process;
 use std::error::Error;
+use std::thread;
+use tinyml::ast::AstNode;
 use tinyml::ast_visitor::Visitable;
+use tinyml::diagnostic::Diagnostic;
+use tinyml::span::SourceMap;
 use tinyml::util::read_file;
 use tinyml::lexer::Lexer;
 use tinyml::parser::Parser;
 use tinyml::passes::{
     visit_def::DefVisitor,
-    visit_debug::DebugVisitor
+    visit_debug::DebugVisitor,
+    visit_infer::InferVisitor,
+    visit_eval::EvalVisitor,
 }; 
 
+/// Stack size of the thread running the program. The passes recurse over
+/// the AST and the interpreter over the calls it evaluates, so deeply
+/// recursive programs need much more than the default.
+const STACK_SIZE: usize = 1 << 30;
 
-fn run(source: String) -> Result<(), Box<dyn Error>> {
+fn run(files: &SourceMap, file: usize, debug: bool) -> Result<(), Vec<Diagnostic>> {
     
     // create the lexer
-    let mut lexer = Lexer::new(source); 
+    let source = files.source(file).unwrap_or_default().to_string();
+    let mut lexer = Lexer::new(source, file); 
     
     // get token stream
-    let tokens = lexer.tokenize();
+    let tokens = lexer
+        .tokenize()
+        .map_err(|errors| errors.into_iter().map(Diagnostic::from).collect::<Vec<_>>())?;
+    if debug {
+        tokens.iter().for_each(|x| println!(" == {:?}", x.ty));
+    }
     
     // move tokens into the parser
     let mut parser = Parser::new(tokens); 
     
     // parse and return the parse tree
-    let ast = parser.parse()?;
-    
-    // build the AST visitor
-    let mut debug_visitor = DebugVisitor::new();
+    let (ast, errors) = parser.parse();
+    if !errors.is_empty() {
+        return Err(errors.into_iter().map(Diagnostic::from).collect());
+    }
     
-    // visit the ast
-    debug_visitor.visit(&*ast)?;
+    if debug {
+        // build the AST visitor
+        let mut debug_visitor = DebugVisitor::new();
+        
+        // visit the ast
+        debug_visitor.visit(&ast).map_err(|d| vec![d])?;
+    }
     
     // create a visitor to define symbols 
     let mut def_visitor = DefVisitor::new();
-    def_visitor.visit(&*ast)?;
+    def_visitor.visit(&ast).map_err(|d| vec![d])?;
+
+    // infer the type of each declaration and run it before the next one,
+    // so every binding is shown as it was when it was made
+    let mut infer_visitor = InferVisitor::new();
+    // leave part of the stack for the frames between two calls
+    let mut eval_visitor = EvalVisitor::with_stack_limit(STACK_SIZE / 4 * 3);
+    let AstNode::Program(decls, _) = &*ast else {
+        unreachable!("the parser always returns a program");
+    };
+    for decl in decls {
+        infer_visitor.visit(decl).map_err(|d| vec![d])?;
+        let mut schemes = infer_visitor.take_bindings();
+        eval_visitor.visit(decl).map_err(|d| vec![d])?;
+        for (name, value) in eval_visitor.take_bindings() {
+            // A declaration can bind a name twice, e.g. in the public part
+            // of a 'local', so take the types in order
+            match schemes.iter().position(|(n, _)| *n == name) {
+                Some(i) => println!("val {} = {} : {}", name, value, schemes.remove(i).1),
+                None => println!("val {} = {}", name, value),
+            }
+        }
+    }
 
     Ok(())
 }
@@ -42,20 +84,31 @@ fn run(source: String) -> Result<(), Box<dyn Error>> {
 fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = env::args().collect();
     
-    if args.len() < 2 {
-        eprintln!("Usage: {} <filepath>", args[0]);
+    let debug = args.iter().any(|a| a == "--debug");
+    let paths: Vec<&String> = args.iter().skip(1).filter(|a| *a != "--debug").collect();
+    if paths.is_empty() {
+        eprintln!("Usage: {} [--debug] <filepath>", args[0]);
         process::exit(1);
     }
     
     // read source file
-    let filepath = &args[1];
-    let source = read_file(filepath).unwrap();
+    let filepath = paths[0];
+    let source = read_file(filepath)?;
     
-    if let Err(e) = run(source) {
-        let display_error = format!("{}", e); // not sure how else to call display
-        return Err(display_error.into())
+    let mut files = SourceMap::new();
+    let file = files.add(filepath, source);
+    let result = thread::scope(|scope| {
+        thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, || run(&files, file, debug))
+            .map(|handle| handle.join().expect("the interpreter thread panicked"))
+    })?;
+    if let Err(diagnostics) = result {
+        for diagnostic in diagnostics {
+            eprintln!("{}", diagnostic.render(&files));
+        }
+        process::exit(1);
     } 
 
     Ok(())
 }
-
@@ -1,12 +1,14 @@
 use std::fmt;
+use crate::span::Span;
+use crate::diagnostic::Diagnostic;
 
 #[macro_export]
 macro_rules! parse_error {
     ($kind:expr, $msg:expr) => {
         $crate::ParseError::new($kind, $msg.to_string(), None)
     };
-    ($kind:expr, $msg:expr, $pos:expr) => {
-        $crate::ParseError::new($kind, $msg.to_string(), Some($pos))
+    ($kind:expr, $msg:expr, $span:expr) => {
+        $crate::ParseError::new($kind, $msg.to_string(), Some($span))
     };
 }
 
@@ -35,16 +37,16 @@ impl fmt::Display for ErrKind {
 pub struct ParseError {
     pub kind: ErrKind,
     pub msg: String,
-    pub pos: Option<usize>,
+    pub span: Option<Span>,
 }
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let pos_display = match self.pos {
-            Some(pos) => pos.to_string(),
+        let pos_display = match self.span {
+            Some(span) => format!("line {}, column {}", span.line, span.col),
             None => "unknown".to_string(),
         }; 
-        write!(f, "ParseError: {} - {} (position: {})", self.kind, self.msg, pos_display)
+        write!(f, "ParseError: {} - {} ({})", self.kind, self.msg, pos_display)
     }
 }
 
@@ -52,12 +54,21 @@ impl std::error::Error for ParseError {}
 
 // Direct constructors are simpler than using a separate params struct
 impl ParseError {
-    pub fn new(kind: ErrKind, msg: String, pos: Option<usize>) -> Self {
-        Self { kind, msg, pos }
+    pub fn new(kind: ErrKind, msg: String, span: Option<Span>) -> Self {
+        Self { kind, msg, span }
     }
     
-    pub fn with_pos(kind: ErrKind, msg: String, pos: usize) -> Self {
-        Self { kind, msg, pos: Some(pos) }
+    pub fn with_span(kind: ErrKind, msg: String, span: Span) -> Self {
+        Self { kind, msg, span: Some(span) }
     }
 }
 
+impl From<ParseError> for Diagnostic {
+    fn from(err: ParseError) -> Self {
+        let diagnostic = Diagnostic::error(err.kind.to_string());
+        match err.span {
+            Some(span) => diagnostic.with_primary(span, err.msg),
+            None => diagnostic.with_note(err.msg),
+        }
+    }
+}
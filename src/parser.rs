@@ -1,17 +1,50 @@
-use crate::{ast::{AstNode, LiteralValue, AstPattern, Type, BinOp}, lexer::{Token, TokenType}};
+use crate::{ast::{AstNode, LiteralValue, AstPattern, ConstructorDecl, FunBinding, Type, TypeAnnotation, BinOp, Assoc, Fixity, BUILTIN_EXCEPTIONS}, lexer::{Token, TokenType}};
+use crate::span::Span;
 use crate::parse_error;
 use crate::parse_error::{ParseError, ErrKind};
+use std::collections::{HashMap, HashSet};
 use std::result::Result;
 
 #[derive(Debug)]
 pub struct Parser {
     tokens: Vec<Token>,
     pos: usize,
+    /// errors recovered from so far; parsing carries on after each one.
+    errors: Vec<ParseError>,
+    /// names declared so far, one scope per enclosing `let` or part of a
+    /// `local` on top of the top-level one.
+    scopes: Vec<Scope>,
+}
+
+/// The names declared in one scope that change how later code is parsed.
+#[derive(Debug, Default)]
+struct Scope {
+    /// infix operators, which expressions are parsed according to.
+    fixities: HashMap<String, Fixity>,
+    /// datatype and exception constructors, which patterns treat as
+    /// constructors rather than variables.
+    constructors: HashSet<String>,
+}
+
+impl Scope {
+    /// Add the names of `inner`, replacing any declared here before.
+    fn extend(&mut self, inner: Scope) {
+        self.fixities.extend(inner.fixities);
+        self.constructors.extend(inner.constructors);
+    }
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self { 
-        Parser { tokens, pos: 0 }
+        Parser {
+            tokens,
+            pos: 0,
+            errors: Vec::new(),
+            scopes: vec![Scope {
+                fixities: builtin_fixities(),
+                constructors: BUILTIN_EXCEPTIONS.iter().map(|name| name.to_string()).collect(),
+            }],
+        }
     }
 
     fn peek(&self) -> Option<TokenType> {
@@ -28,36 +61,195 @@ impl Parser {
         }
     }
     
+    /// Span of the token at index `pos`, or an empty span just after the last
+    /// token if `pos` is past the end of the input.
+    fn span_at(&self, pos: usize) -> Span {
+        match self.tokens.get(pos) {
+            Some(token) => token.span(),
+            None => self.tokens
+                .last()
+                .map(|token| token.span().after())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Span from the token at `start` up to the last consumed token.
+    fn span_from(&self, start: usize) -> Span {
+        if self.pos > start {
+            self.span_at(start).to(&self.span_at(self.pos - 1))
+        } else {
+            self.span_at(start)
+        }
+    }
+    
+    /// Consume the next token if it is `expected`. Otherwise it is left in
+    /// place, so recovery can see it, e.g. the `val` after a missing ')'.
     fn expect(&mut self, expected: TokenType) -> Result<(), ParseError> {
-        let pos = self.pos;
-        if let Some(token) = self.consume() {
+        let span = self.span_at(self.pos);
+        if let Some(token) = self.tokens.get(self.pos) {
             if token.ty == expected {
+                self.pos += 1;
                 Ok(())
             } else {
                 let msg = format!("Expected '{:?}', got '{:?}'", expected, token.ty);
-                Err(parse_error!(ErrKind::UnexpectedToken, msg, pos)) 
+                Err(parse_error!(ErrKind::UnexpectedToken, msg, span)) 
             }
         } else {
             let msg = format!("Expected '{:?}', got EOF", expected);
-            Err(parse_error!(ErrKind::UnexpectedToken, msg, pos))
+            Err(parse_error!(ErrKind::UnexpectedToken, msg, span))
         }
     }
 
-    pub fn parse(&mut self) -> Result<Box<AstNode>, ParseError> {
-        self.tokens.iter().for_each(|x| println!(" == {:?}", x.ty));
-        
+    /// Parse the whole token stream. Errors do not stop the parser: each one
+    /// is recorded, the broken code becomes an `AstNode::Error` and parsing
+    /// resumes, so all errors in a file are returned together.
+    pub fn parse(&mut self) -> (Box<AstNode>, Vec<ParseError>) {
         let mut decls = Vec::new();
         while self.peek().is_some() {
-            for decl in self.parse_decls()? {
+            for decl in self.parse_decls() {
                 decls.push(Box::new(decl));
             }
         }
-        Ok(Box::new(AstNode::Program(decls)))
+        let span = self.span_from(0);
+        (Box::new(AstNode::Program(decls, span)), std::mem::take(&mut self.errors))
+    }
+
+    fn at_decl_start(&self) -> bool {
+        matches!(self.peek(),
+            Some(TokenType::Val) |
+            Some(TokenType::Fun) |
+            Some(TokenType::Datatype) |
+            Some(TokenType::Exception) |
+            Some(TokenType::Local) |
+            Some(TokenType::Infix) |
+            Some(TokenType::Infixl) |
+            Some(TokenType::Infixr))
+    }
+
+    /// Record `err` and skip to where the next declaration can start: a
+    /// `val`/`fun` outside of any brackets or `let`, or a `;`. At least one
+    /// token is always skipped so the parser makes progress.
+    fn recover_decl(&mut self, err: ParseError, start: usize) -> AstNode {
+        self.errors.push(err);
+        let mut depth = 0usize;
+        while let Some(token) = self.peek() {
+            let boundary = self.at_decl_start() || token == TokenType::SemiColon;
+            if boundary && depth == 0 && self.pos > start {
+                break;
+            }
+            match token {
+                TokenType::LeftParen | TokenType::LeftBracket | TokenType::LeftBrace
+                | TokenType::Let | TokenType::Local => depth += 1,
+                TokenType::RightParen | TokenType::RightBracket | TokenType::RightBrace | TokenType::End => {
+                    depth = depth.saturating_sub(1)
+                },
+                _ => {},
+            }
+            self.consume();
+        }
+        AstNode::Error(self.span_from(start))
+    }
+
+    /// Record `err` and skip past the `close` token ending the bracketed
+    /// construct that started at `start`. Stops early at a closing token of
+    /// an enclosing construct, or at a `val`/`fun` when `close` was never
+    /// written.
+    fn recover_expr(&mut self, err: ParseError, start: usize, close: TokenType) -> AstNode {
+        self.errors.push(err);
+        let mut depth = 0usize;
+        while let Some(token) = self.peek() {
+            if depth == 0 {
+                if token == close {
+                    self.consume();
+                    break;
+                }
+                let closes = matches!(token,
+                    TokenType::RightParen | TokenType::RightBracket | TokenType::RightBrace | TokenType::End);
+                if closes || (self.at_decl_start() && close != TokenType::End) {
+                    break;
+                }
+            }
+            match token {
+                TokenType::LeftParen | TokenType::LeftBracket | TokenType::LeftBrace
+                | TokenType::Let | TokenType::Local => depth += 1,
+                TokenType::RightParen | TokenType::RightBracket | TokenType::RightBrace
+                | TokenType::End => depth -= 1,
+                _ => {},
+            }
+            self.consume();
+        }
+        AstNode::Error(self.span_from(start))
+    }
+
+    fn parse_type_annotation(&mut self) -> Result<TypeAnnotation, ParseError> {
+        let pos = self.pos;
+        let ty = self.parse_type()?;
+        Ok(TypeAnnotation { ty, span: self.span_from(pos) })
     }
 
     fn parse_type(&mut self) -> Result<Type, ParseError> {
+        // '->' binds loosest and associates to the right
+        let param = self.parse_product_type()?;
+        if let Some(TokenType::Arrow) = self.peek() {
+            self.consume(); // Consume '->'
+            let ret = self.parse_type()?;
+            Ok(Type::Arrow(Box::new(param), Box::new(ret)))
+        } else {
+            Ok(param)
+        }
+    }
+
+    fn parse_product_type(&mut self) -> Result<Type, ParseError> {
+        let first = self.parse_app_type()?;
+        if self.peek() != Some(TokenType::Multiply) {
+            return Ok(first);
+        }
+        let mut types = vec![first];
+        while let Some(TokenType::Multiply) = self.peek() {
+            self.consume(); // Consume '*'
+            types.push(self.parse_app_type()?);
+        }
+        Ok(Type::Product(types))
+    }
+
+    fn parse_app_type(&mut self) -> Result<Type, ParseError> {
+        let mut args = match self.peek() {
+            Some(TokenType::LeftParen) => {
+                self.consume(); // Consume '('
+                let mut types = vec![self.parse_type()?];
+                while let Some(TokenType::Comma) = self.peek() {
+                    self.consume(); // Consume ','
+                    types.push(self.parse_type()?);
+                }
+                self.expect(TokenType::RightParen)?;
+                types
+            },
+            _ => vec![self.parse_atom_type()?],
+        };
+
+        // Postfix type constructors, e.g. `int list` or `(int, bool) pair`.
+        // `ref` is a keyword, but names a type constructor here too
+        loop {
+            let name = match self.peek() {
+                Some(TokenType::Id(name)) => name,
+                Some(TokenType::Ref) => "ref".to_string(),
+                _ => break,
+            };
+            self.consume();
+            args = vec![Type::Con(name, args)];
+        }
+
+        if args.len() == 1 {
+            Ok(args.remove(0))
+        } else {
+            // `(t1, t2)` without a type constructor is a product
+            Ok(Type::Product(args))
+        }
+    }
+
+    fn parse_atom_type(&mut self) -> Result<Type, ParseError> {
         let pos = self.pos;
-        let prefix = match self.peek() {
+        match self.peek() {
             Some(TokenType::TypeInt) => {
                 self.consume();
                 Ok(Type::Int)
@@ -70,57 +262,62 @@ impl Parser {
                 self.consume();
                 Ok(Type::Char)
             },
+            Some(TokenType::TypeReal) => {
+                self.consume();
+                Ok(Type::Real)
+            },
             Some(TokenType::TypeString) => {
                 self.consume();
                 Ok(Type::String)
             },
+            Some(TokenType::Id(name)) if name == "unit" => {
+                self.consume();
+                Ok(Type::Unit)
+            },
+            Some(TokenType::LeftBrace) => {
+                self.consume(); // Consume '{'
+                let (fields, _) = self.parse_record_fields(false, |parser, _, _| {
+                    parser.expect(TokenType::Colon)?;
+                    parser.parse_type()
+                })?;
+                Ok(Type::record(fields))
+            },
+            Some(TokenType::Id(name)) => {
+                self.consume();
+                Ok(Type::Con(name, Vec::new()))
+            },
             Some(TokenType::SingleQuote) => {
                 self.consume();
+                // An equality type variable ''a is kept as the variable 'a
+                let prefix = if self.peek() == Some(TokenType::SingleQuote) {
+                    self.consume();
+                    "'"
+                } else {
+                    ""
+                };
                 match self.peek() {
                     Some(TokenType::Id(id)) => {
                         self.consume();
-                        Ok(Type::Var(id))
+                        Ok(Type::Var(format!("{}{}", prefix, id)))
                     },
                     Some(token) => {
                         let msg = format!("Expected identifier for type name, recieved: {token}");
-                        Err(parse_error!(ErrKind::UnexpectedToken, msg, pos))
+                        Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(pos)))
                     },
                     None => {
                         let msg = "Unexpected EOF";
-                        Err(parse_error!(ErrKind::UnexpectedToken, msg, pos))
+                        Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(pos)))
                     }
                  }
             },
-            Some(TokenType::LeftParen) => {
-                self.consume(); // Consume '('
-                let type1 = self.parse_type()?;
-                self.expect(TokenType::Comma)?;
-                let type2 = self.parse_type()?;
-                self.expect(TokenType::RightParen)?;
-                Ok(Type::Product(Box::new(type1), Box::new(type2)))
-            },
             Some(token) => {
                 let msg = format!("Expected a type, got {:?}", token);
-                Err(parse_error!(ErrKind::UnexpectedToken, msg, pos))
+                Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(pos)))
             }
             None => {
                 let msg ="Expected a type, got EOF";
-                Err(parse_error!(ErrKind::UnexpectedToken, msg, pos))
+                Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(pos)))
             }
-        }?;
-
-        match self.peek() {
-            Some(TokenType::Arrow) => {
-                self.consume(); // Consume '->'
-                let return_type = self.parse_type()?;
-                Ok(Type::Arrow(Box::new(prefix), Box::new(return_type)))
-            },
-            Some(TokenType::Multiply) => {
-                self.consume(); // Consume '*'
-                let second_type = self.parse_type()?;
-                Ok(Type::Product(Box::new(prefix), Box::new(second_type)))
-            },
-            _ => Ok(prefix), // No suffix, just return the prefix type
         }
     }
 
@@ -129,71 +326,170 @@ impl Parser {
         match self.peek() {
             Some(TokenType::Integer(n)) => {
                 self.consume();
-                Ok(AstNode::Literal(LiteralValue::Integer(n)))
+                Ok(AstNode::Literal(LiteralValue::Integer(n), self.span_from(pos)))
+            },
+            Some(TokenType::Float(x)) => {
+                self.consume();
+                Ok(AstNode::Literal(LiteralValue::Real(x), self.span_from(pos)))
             },
             Some(TokenType::Bool(b)) => {
                 self.consume();
-                Ok(AstNode::Literal(LiteralValue::Boolean(b)))
+                Ok(AstNode::Literal(LiteralValue::Boolean(b), self.span_from(pos)))
             },
             Some(TokenType::String(s)) => {
                 self.consume();
-                Ok(AstNode::Literal(LiteralValue::String(s)))
+                Ok(AstNode::Literal(LiteralValue::String(s), self.span_from(pos)))
+            },
+            Some(TokenType::Char(c)) => {
+                self.consume();
+                Ok(AstNode::Literal(LiteralValue::Char(c), self.span_from(pos)))
+            },
+            Some(TokenType::Id(id)) if self.fixity_of(&id).is_some() => {
+                let msg = format!("Infix operator '{}' is missing an operand, write 'op {}' to use it as a function", id, id);
+                Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(pos)))
             },
             Some(TokenType::Id(id)) => {
                 self.consume();
-                Ok(AstNode::Id(id))
+                Ok(AstNode::Id(id, self.span_from(pos)))
             },
-            Some(TokenType::LeftParen) => {
-                self.consume(); 
-                if let Some(TokenType::RightParen) = self.peek() {
-                    self.consume(); // Consume ')'
-                    return Ok(AstNode::Tuple(Vec::new()));
-                }
-                
-                let expr = self.parse_expr()?; 
-                if let Some(TokenType::Comma) = self.peek() {
+            Some(TokenType::Op) => {
+                self.consume(); // Consume 'op'
+                if self.peek() == Some(TokenType::Tilde) {
                     self.consume();
-                    let mut expressions = vec![Box::new(expr)]; 
-                    expressions.push(Box::new(self.parse_expr()?)); 
-                    while let Some(TokenType::Comma) = self.peek() {
-                        self.consume(); // Consume ','
-                        expressions.push(Box::new(self.parse_expr()?));
-                    } 
-                    self.expect(TokenType::RightParen)?;
-                    Ok(AstNode::Tuple(expressions))
+                    return Ok(builtin_neg_fn(self.span_from(pos)));
+                }
+                let Some(name) = self.peek().as_ref().and_then(operator_name) else {
+                    let msg = "Expected an operator after 'op'";
+                    return Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(self.pos)));
+                };
+                self.consume();
+                let span = self.span_from(pos);
+                if is_builtin_op(&name) {
+                    Ok(builtin_op_fn(&name, span))
                 } else {
-                    self.expect(TokenType::RightParen)?;
-                    Ok(expr)
+                    Ok(AstNode::Id(name, span))
                 }
             },
+            Some(TokenType::Nil) => {
+                self.consume();
+                Ok(AstNode::List(Vec::new(), self.span_from(pos)))
+            },
+            Some(TokenType::Tilde) => {
+                self.consume();
+                Ok(builtin_neg_fn(self.span_from(pos)))
+            },
+            Some(TokenType::Ref) => {
+                self.consume();
+                Ok(AstNode::Id("ref".to_string(), self.span_from(pos)))
+            },
+            Some(TokenType::Some) => {
+                self.consume();
+                Ok(AstNode::Id("some".to_string(), self.span_from(pos)))
+            },
+            Some(TokenType::None) => {
+                self.consume();
+                Ok(AstNode::Id("none".to_string(), self.span_from(pos)))
+            },
+            Some(TokenType::LeftParen) => {
+                self.consume(); 
+                let expr = self.parse_paren(pos);
+                Ok(expr.unwrap_or_else(|err| self.recover_expr(err, pos, TokenType::RightParen)))
+            },
+            Some(TokenType::Let) => {
+                // 'let ... end' is closed by its 'end', so it is an atom
+                self.consume(); // Eat 'let'
+                // Operators and constructors declared inside the 'let' go out
+                // of scope at its 'end'
+                self.scopes.push(Scope::default());
+                let expr = self.parse_let(pos);
+                let expr = expr.unwrap_or_else(|err| self.recover_expr(err, pos, TokenType::End));
+                self.scopes.pop();
+                Ok(expr)
+            },
             Some(TokenType::LeftBracket) => { // Fixed from LeftParen to LeftBracket
                 self.consume(); // Consume '['
-                
-                if let Some(TokenType::RightBracket) = self.peek() {
-                    self.consume(); // Consume ']'
-                    return Ok(AstNode::List(Vec::new()));
-                }
-                let mut items = Vec::new();
-                items.push(Box::new(self.parse_expr()?));
-                while let Some(TokenType::Comma) = self.peek() {
-                    self.consume(); // Consume ','
-                    items.push(Box::new(self.parse_expr()?));
-                }
-                self.expect(TokenType::RightBracket)?;
-                Ok(AstNode::List(items))
+                let list = self.parse_list(pos);
+                Ok(list.unwrap_or_else(|err| self.recover_expr(err, pos, TokenType::RightBracket)))
+            },
+            Some(TokenType::LeftBrace) => {
+                self.consume(); // Consume '{'
+                let fields = self.parse_record_fields(false, |parser, _, _| {
+                    parser.expect(TokenType::Equal)?;
+                    Ok(Box::new(parser.parse_expr()?))
+                });
+                Ok(match fields {
+                    Ok((fields, _)) => AstNode::Record { fields, span: self.span_from(pos) },
+                    Err(err) => self.recover_expr(err, pos, TokenType::RightBrace),
+                })
+            },
+            Some(TokenType::Hash) => {
+                self.consume(); // Consume '#'
+                let label = self.parse_label()?;
+                Ok(AstNode::Selector { label, span: self.span_from(pos) })
             },
             Some(token) => {
                 let msg = format!("Expected an atom, got {:?}", token);
-                Err(parse_error!(ErrKind::UnexpectedToken, msg, pos))
+                Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(pos)))
             }
             None => {
                 let msg = "Expected an atom, got EOF";
-                Err(parse_error!(ErrKind::UnexpectedToken, msg, pos))
+                Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(pos)))
             },
         }
     }
 
+    /// The rest of a `(...)` group after the '(': unit, a tuple, a sequence
+    /// or a parenthesised expression.
+    fn parse_paren(&mut self, pos: usize) -> Result<AstNode, ParseError> {
+        if let Some(TokenType::RightParen) = self.peek() {
+            self.consume(); // Consume ')'
+            return Ok(AstNode::Tuple(Vec::new(), self.span_from(pos)));
+        }
+        
+        let expr = self.parse_expr()?; 
+        if let Some(TokenType::SemiColon) = self.peek() {
+            let mut exprs = vec![Box::new(expr)];
+            while let Some(TokenType::SemiColon) = self.peek() {
+                self.consume(); // Consume ';'
+                exprs.push(Box::new(self.parse_expr()?));
+            }
+            self.expect(TokenType::RightParen)?;
+            return Ok(AstNode::Seq(exprs, self.span_from(pos)));
+        }
+        if let Some(TokenType::Comma) = self.peek() {
+            self.consume();
+            let mut expressions = vec![Box::new(expr)]; 
+            expressions.push(Box::new(self.parse_expr()?)); 
+            while let Some(TokenType::Comma) = self.peek() {
+                self.consume(); // Consume ','
+                expressions.push(Box::new(self.parse_expr()?));
+            } 
+            self.expect(TokenType::RightParen)?;
+            Ok(AstNode::Tuple(expressions, self.span_from(pos)))
+        } else {
+            self.expect(TokenType::RightParen)?;
+            Ok(expr)
+        }
+    }
+
+    /// The rest of a list literal after the '['.
+    fn parse_list(&mut self, pos: usize) -> Result<AstNode, ParseError> {
+        if let Some(TokenType::RightBracket) = self.peek() {
+            self.consume(); // Consume ']'
+            return Ok(AstNode::List(Vec::new(), self.span_from(pos)));
+        }
+        let mut items = Vec::new();
+        items.push(Box::new(self.parse_expr()?));
+        while let Some(TokenType::Comma) = self.peek() {
+            self.consume(); // Consume ','
+            items.push(Box::new(self.parse_expr()?));
+        }
+        self.expect(TokenType::RightBracket)?;
+        Ok(AstNode::List(items, self.span_from(pos)))
+    }
+
     fn parse_app_expr(&mut self) -> Result<AstNode, ParseError> {
+        let pos = self.pos;
         let mut expr = self.parse_atom()?;
         
         // Keep applying atoms as long as we see them
@@ -203,6 +499,7 @@ impl Parser {
             expr = AstNode::App {
                 func: Box::new(expr),
                 arg: Box::new(atom),
+                span: self.span_from(pos),
             };
         }
         
@@ -211,107 +508,169 @@ impl Parser {
 
     // Helper method to check if the next token could start an atom
     fn could_start_atom(&self) -> bool {
-        match self.peek() {
+        // An infix operator continues the expression rather than being an argument
+        if let Some(TokenType::Id(name)) = self.peek() {
+            return self.fixity_of(&name).is_none();
+        }
+        matches!(self.peek(),
+            Some(TokenType::Op) |
             Some(TokenType::Integer(_)) |
+            Some(TokenType::Float(_)) |
             Some(TokenType::Bool(_)) |
             Some(TokenType::String(_)) |
+            Some(TokenType::Char(_)) |
             Some(TokenType::Id(_)) |
+            Some(TokenType::Nil) |
+            Some(TokenType::Some) |
+            Some(TokenType::None) |
+            Some(TokenType::Tilde) |
+            Some(TokenType::Ref) |
+            Some(TokenType::Let) |
             Some(TokenType::LeftParen) |
-            Some(TokenType::LeftBracket) => true,
-            _ => false,
+            Some(TokenType::LeftBracket) |
+            Some(TokenType::LeftBrace) |
+            Some(TokenType::Hash))
+    }
+
+    /// A record label: an alphanumeric identifier, or a positive integer
+    /// as in the labels `1` to `n` of a tuple.
+    fn parse_label(&mut self) -> Result<String, ParseError> {
+        let pos = self.pos;
+        match self.peek() {
+            Some(TokenType::Id(name)) if name.starts_with(char::is_alphabetic) => {
+                self.consume();
+                Ok(name)
+            },
+            Some(TokenType::Integer(n)) if !n.starts_with(['0', '~']) => {
+                self.consume();
+                Ok(n)
+            },
+            Some(token) => {
+                let msg = format!("Expected a record label, got '{:?}'", token);
+                Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(pos)))
+            },
+            None => {
+                let msg = "Expected a record label, got EOF";
+                Err(parse_error!(ErrKind::UnexpectedEOF, msg, self.span_at(pos)))
+            },
         }
     }
 
-    fn parse_mul_expr(&mut self) -> Result<AstNode, ParseError> {
-        let mut left = self.parse_app_expr()?;
-        
+    /// The fields of a record expression, type or pattern after the '{', up
+    /// to and including the '}'. `field` parses what follows each label,
+    /// given the label and where it starts. The flag returned is set if the
+    /// fields end in '...', which is only accepted if `flexible`.
+    fn parse_record_fields<T>(
+        &mut self,
+        flexible: bool,
+        mut field: impl FnMut(&mut Self, &str, usize) -> Result<T, ParseError>,
+    ) -> Result<(Vec<(String, T)>, bool), ParseError> {
+        let mut fields: Vec<(String, T)> = Vec::new();
+        if let Some(TokenType::RightBrace) = self.peek() {
+            self.consume(); // Consume '}'
+            return Ok((fields, false));
+        }
         loop {
-            match self.peek() {
-                Some(TokenType::Multiply) => {
-                    self.consume();
-                    let right = self.parse_app_expr()?;
-                    left = AstNode::BinOp {
-                        left: Box::new(left),
-                        op: BinOp::Mul,
-                        right: Box::new(right),
-                    };
-                },
-                Some(TokenType::Divide) => {
-                    self.consume();
-                    let right = self.parse_app_expr()?;
-                    left = AstNode::BinOp {
-                        left: Box::new(left),
-                        op: BinOp::Div,
-                        right: Box::new(right),
-                    };
-                },
-                _ => break,
+            if flexible && self.peek() == Some(TokenType::Ellipsis) {
+                self.consume(); // Consume '...'
+                self.expect(TokenType::RightBrace)?;
+                return Ok((fields, true));
+            }
+            let pos = self.pos;
+            let label = self.parse_label()?;
+            if fields.iter().any(|(seen, _)| *seen == label) {
+                let msg = format!("Label '{}' appears more than once in the record", label);
+                return Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(pos)));
+            }
+            let value = field(self, &label, pos)?;
+            fields.push((label, value));
+            
+            if let Some(TokenType::Comma) = self.peek() {
+                self.consume(); // Consume ','
+            } else {
+                self.expect(TokenType::RightBrace)?;
+                return Ok((fields, false));
             }
         }
-        
-        Ok(left)
     }
 
-    fn parse_add_expr(&mut self) -> Result<AstNode, ParseError> {
-        let mut left = self.parse_mul_expr()?;
+    /// The fixity of `name` in the innermost scope that declares one.
+    fn fixity_of(&self, name: &str) -> Option<Fixity> {
+        self.scopes.iter().rev().find_map(|scope| scope.fixities.get(name).copied())
+    }
+
+    /// Whether `name` is a constructor in the current scope.
+    fn is_constructor(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.constructors.contains(name))
+    }
+
+    /// The innermost scope, which declarations add their names to.
+    fn scope(&mut self) -> &mut Scope {
+        self.scopes.last_mut().expect("the top-level scope is never popped")
+    }
+
+    /// The next token's operator name and fixity, if it is an infix operator.
+    fn peek_infix(&self) -> Option<(String, Fixity)> {
+        let name = operator_name(&self.peek()?)?;
+        let fixity = self.fixity_of(&name)?;
+        Some((name, fixity))
+    }
+
+    /// Parse applications joined by infix operators, by precedence climbing:
+    /// only operators binding at least as tight as `min_prec` are taken here,
+    /// tighter ones on the right are left to the recursive call.
+    fn parse_infix_expr(&mut self, min_prec: u8) -> Result<AstNode, ParseError> {
+        let pos = self.pos;
+        let mut left = self.parse_app_expr()?;
         
-        loop {
-            match self.peek() {
-                Some(TokenType::Plus) => {
-                    self.consume();
-                    let right = self.parse_mul_expr()?;
-                    left = AstNode::BinOp {
-                        left: Box::new(left),
-                        op: BinOp::Add,
-                        right: Box::new(right),
-                    };
-                },
-                Some(TokenType::Minus) => {
-                    self.consume();
-                    let right = self.parse_mul_expr()?;
-                    left = AstNode::BinOp {
-                        left: Box::new(left),
-                        op: BinOp::Sub,
-                        right: Box::new(right),
-                    };
-                },
-                _ => break,
+        while let Some((name, fixity)) = self.peek_infix() {
+            if fixity.prec < min_prec {
+                break;
             }
+            let op_span = self.span_at(self.pos);
+            self.consume(); // Consume the operator
+            let next_prec = match fixity.assoc {
+                Assoc::Left => fixity.prec + 1,
+                Assoc::Right => fixity.prec,
+            };
+            let right = self.parse_infix_expr(next_prec)?;
+            left = infix_node(&name, op_span, left, right, self.span_from(pos));
         }
         
         Ok(left)
     }
     
-    fn parse_comp_expr(&mut self) -> Result<AstNode, ParseError> {
-        let mut left = self.parse_add_expr()?;
-        
-        loop {
-            let op = match self.peek() {
-                Some(TokenType::Less)           => Some(BinOp::Lt),
-                Some(TokenType::StrictLess)     => Some(BinOp::Lte),
-                Some(TokenType::Greater)        => Some(BinOp::Gt),
-                Some(TokenType::StrictGreater)  => Some(BinOp::Gte),
-                Some(TokenType::CompEqual)      => Some(BinOp::Eq),
-                Some(TokenType::CompNotEqual)   => Some(BinOp::Eq),
-                _ => None,
+    fn parse_orelse_expr(&mut self) -> Result<AstNode, ParseError> {
+        // 'orelse' binds looser than 'andalso', and both looser than any operator
+        let pos = self.pos;
+        let mut left = self.parse_andalso_expr()?;
+        while let Some(TokenType::OrElse) = self.peek() {
+            self.consume(); // Consume 'orelse'
+            let right = self.parse_andalso_expr()?;
+            left = AstNode::OrElse {
+                left: Box::new(left),
+                right: Box::new(right),
+                span: self.span_from(pos),
             };
-            
-            if let Some(bin_op) = op {
-                self.consume();
-                let right = self.parse_add_expr()?;
-                left = AstNode::BinOp {
-                    left: Box::new(left),
-                    op: bin_op,
-                    right: Box::new(right),
-                };
-            } else {
-                break;
-            }
         }
-        
         Ok(left)
     }
-    
+
+    fn parse_andalso_expr(&mut self) -> Result<AstNode, ParseError> {
+        let pos = self.pos;
+        let mut left = self.parse_infix_expr(0)?;
+        while let Some(TokenType::AndAlso) = self.peek() {
+            self.consume(); // Consume 'andalso'
+            let right = self.parse_infix_expr(0)?;
+            left = AstNode::AndAlso {
+                left: Box::new(left),
+                right: Box::new(right),
+                span: self.span_from(pos),
+            };
+        }
+        Ok(left)
+    }
+
     fn parse_match(&mut self) -> Result<Vec<(AstPattern, Box<AstNode>)>, ParseError> {
         let pos = self.pos;
         let mut arms = Vec::new();
@@ -332,7 +691,7 @@ impl Parser {
         
         if arms.is_empty() {
             let msg = "Match expression must have at least one arm";
-            return Err(parse_error!(ErrKind::UnexpectedToken, msg, pos))
+            return Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(pos)))
         }
         
         Ok(arms)
@@ -352,45 +711,119 @@ impl Parser {
                     cond,
                     then: then_expr,
                     else_: else_expr,
+                    span: self.span_from(pos),
                 })
             },
-            Some(TokenType::Let) => {
-                self.consume(); // Eat 'let'
-                let decl = Box::new(self.parse_decl()?);
-                self.expect(TokenType::In)?;
-                let body = Box::new(self.parse_expr()?);
-                self.expect(TokenType::End)?;
-                Ok(AstNode::Let {
-                    decl,
-                    body,
+            Some(TokenType::Case) => {
+                self.consume(); // Eat 'case'
+                let scrutinee = Box::new(self.parse_expr()?);
+                self.expect(TokenType::Of)?;
+                let arms = self.parse_match()?;
+                Ok(AstNode::Case {
+                    scrutinee,
+                    arms,
+                    span: self.span_from(pos),
                 })
             },
-            Some(TokenType::Fun) => {
+            Some(TokenType::Fn) => {
                 self.consume(); // Eat 'fn'
                 let clauses = self.parse_match()?;
                 Ok(AstNode::Fn {
                     clauses,
+                    span: self.span_from(pos),
+                })
+            },
+            Some(TokenType::Fun) => {
+                // Consumed so that recovery does not take it for the next declaration
+                self.consume();
+                let msg = "'fun' only starts a declaration, anonymous functions are written 'fn p => e'";
+                Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(pos)))
+            },
+            Some(TokenType::Raise) => {
+                self.consume(); // Eat 'raise'
+                let expr = Box::new(self.parse_expr()?);
+                Ok(AstNode::Raise {
+                    expr,
+                    span: self.span_from(pos),
                 })
             },
-            Some(_) => self.parse_comp_expr(),
-            None => Err(parse_error!(ErrKind::UnexpectedToken, "TODO", pos)),
+            Some(_) => {
+                let expr = self.parse_orelse_expr()?;
+                if let Some(TokenType::Handle) = self.peek() {
+                    self.consume(); // Eat 'handle'
+                    let arms = self.parse_match()?;
+                    return Ok(AstNode::Handle {
+                        expr: Box::new(expr),
+                        arms,
+                        span: self.span_from(pos),
+                    });
+                }
+                Ok(expr)
+            },
+            None => Err(parse_error!(ErrKind::UnexpectedToken, "TODO", self.span_at(pos))),
+        }
+    }
+
+    /// The rest of a `let ... in ... end` expression after the 'let'.
+    fn parse_let(&mut self, pos: usize) -> Result<AstNode, ParseError> {
+        let decls = self.parse_decl_seq()?;
+        self.expect(TokenType::In)?;
+        
+        // The body may be a sequence 'e1; e2' without parentheses
+        let body_pos = self.pos;
+        let mut exprs = vec![Box::new(self.parse_expr()?)];
+        while let Some(TokenType::SemiColon) = self.peek() {
+            self.consume(); // Consume ';'
+            exprs.push(Box::new(self.parse_expr()?));
+        }
+        let body = match exprs.len() {
+            1 => exprs.remove(0),
+            _ => Box::new(AstNode::Seq(exprs, self.span_from(body_pos))),
+        };
+        self.expect(TokenType::End)?;
+        Ok(AstNode::Let {
+            decls: decls.into_iter().map(Box::new).collect(),
+            body,
+            span: self.span_from(pos),
+        })
+    }
+
+    /// Declarations up to the next token that can not start one, such as
+    /// the 'in' of a `let`. They may be separated by ';'.
+    fn parse_decl_seq(&mut self) -> Result<Vec<AstNode>, ParseError> {
+        let mut decls = Vec::new();
+        loop {
+            if let Some(TokenType::SemiColon) = self.peek() {
+                self.consume(); // Consume ';'
+            } else if self.at_decl_start() {
+                decls.push(self.parse_decl()?);
+            } else {
+                return Ok(decls);
+            }
         }
     }
 
     fn parse_decl(&mut self) -> Result<AstNode, ParseError> {
-        println!("Peek: {:?}", self.peek());
         let pos = self.pos;
         match self.peek() {
             Some(TokenType::Val) => {
-                println!("Match {:?}", self.peek());
                 self.consume(); // Eat 'val'
+                if let Some(TokenType::Rec) = self.peek() {
+                    self.consume(); // Eat 'rec'
+                    let mut funs = vec![self.parse_val_rec_binding()?];
+                    while let Some(TokenType::And) = self.peek() {
+                        self.consume(); // Eat 'and'
+                        funs.push(self.parse_val_rec_binding()?);
+                    }
+                    return Ok(AstNode::FunGroup { funs, span: self.span_from(pos) });
+                }
                 
                 // More descriptive error for pattern parsing
                 let pat = self.parse_pattern()?;
                 
                 let typ = if self.peek() == Some(TokenType::Colon) {
                     self.consume();
-                    Some(self.parse_type()?)
+                    Some(self.parse_type_annotation()?)
                 } else {
                     None 
                 };
@@ -402,113 +835,689 @@ impl Parser {
                     pat,
                     typ,
                     exp,
+                    span: self.span_from(pos),
                 })
             },
             Some(TokenType::Fun) => {
                 self.consume(); // Eat 'fun'
-                if let Some(TokenType::Id(name)) = self.peek() {
-                    let id = name.clone();
-                    self.consume(); // Consume the ID
-                    
-                    let clauses = self.parse_match()?;
-                    
-                    let typ = if self.peek() == Some(TokenType::Colon) {
+                let mut funs = vec![self.parse_fun_binding()?];
+                while let Some(TokenType::And) = self.peek() {
+                    self.consume(); // Eat 'and'
+                    funs.push(self.parse_fun_binding()?);
+                }
+                
+                let span = self.span_from(pos);
+                if funs.len() > 1 {
+                    return Ok(AstNode::FunGroup { funs, span });
+                }
+                let FunBinding { name, clauses, typ, .. } = funs.remove(0);
+                Ok(AstNode::FunDecl { name, clauses, typ, span })
+            },
+            Some(TokenType::Datatype) => {
+                self.consume(); // Eat 'datatype'
+                let params = self.parse_type_params()?;
+                let name = match self.peek() {
+                    Some(TokenType::Id(name)) => {
                         self.consume();
-                        Some(self.parse_type()?)
-                    } else {
-                        None
-                    };
-                    
-                    Ok(AstNode::FunDecl {
-                        name: id,
-                        clauses,
-                        typ,
-                    })
-                } else {
-                    let msg = "Expected identifier after 'fun'";  
-                    Err(parse_error!(ErrKind::UnexpectedToken, msg, pos))
+                        name
+                    },
+                    _ => {
+                        let msg = "Expected a type name after 'datatype'";
+                        return Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(self.pos)));
+                    },
+                };
+                self.expect(TokenType::Equal)?;
+                
+                let mut constructors = vec![self.parse_constructor_decl()?];
+                while let Some(TokenType::Bar) = self.peek() {
+                    self.consume(); // Consume '|'
+                    constructors.push(self.parse_constructor_decl()?);
                 }
+                
+                Ok(AstNode::DatatypeDecl {
+                    name,
+                    params,
+                    constructors,
+                    span: self.span_from(pos),
+                })
+            },
+            Some(TokenType::Local) => {
+                self.consume(); // Eat 'local'
+                // Names declared in the private part are visible up to the
+                // 'end', those of the public part carry on after it
+                self.scopes.push(Scope::default());
+                let private = self.parse_decl_seq();
+                self.scopes.push(Scope::default());
+                let public = private.and_then(|private| {
+                    self.expect(TokenType::In)?;
+                    let public = self.parse_decl_seq()?;
+                    self.expect(TokenType::End)?;
+                    Ok((private, public))
+                });
+                let exported = self.scopes.pop().unwrap_or_default();
+                self.scopes.pop();
+                self.scope().extend(exported);
+                
+                let (private, public) = public?;
+                Ok(AstNode::Local {
+                    private: private.into_iter().map(Box::new).collect(),
+                    public: public.into_iter().map(Box::new).collect(),
+                    span: self.span_from(pos),
+                })
+            },
+            Some(TokenType::Exception) => {
+                self.consume(); // Eat 'exception'
+                let ConstructorDecl { name, arg, .. } = self.parse_constructor_decl()?;
+                Ok(AstNode::ExceptionDecl {
+                    name,
+                    arg,
+                    span: self.span_from(pos),
+                })
+            },
+            Some(TokenType::Infix) | Some(TokenType::Infixl) | Some(TokenType::Infixr) => {
+                let assoc = match self.consume().map(|token| &token.ty) {
+                    Some(TokenType::Infixr) => Assoc::Right,
+                    _ => Assoc::Left,
+                };
+                let prec = match self.peek() {
+                    Some(TokenType::Integer(n)) => {
+                        let span = self.span_at(self.pos);
+                        self.consume();
+                        match n.parse::<u8>() {
+                            Ok(prec) if prec <= 9 => prec,
+                            _ => {
+                                let msg = format!("Precedence must be between 0 and 9, got {}", n);
+                                return Err(parse_error!(ErrKind::UnexpectedToken, msg, span));
+                            },
+                        }
+                    },
+                    _ => 0,
+                };
+                
+                let mut names = Vec::new();
+                while let Some(name) = self.peek().as_ref().and_then(operator_name) {
+                    self.consume();
+                    names.push(name);
+                }
+                if names.is_empty() {
+                    let msg = "Expected an operator in fixity declaration";
+                    return Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(self.pos)));
+                }
+                
+                let fixity = Fixity { prec, assoc };
+                let scope = self.scope();
+                for name in &names {
+                    scope.fixities.insert(name.clone(), fixity);
+                }
+                Ok(AstNode::FixityDecl {
+                    names,
+                    fixity,
+                    span: self.span_from(pos),
+                })
             },
             Some(token) => {
-                let msg = format!("Expected 'val' or 'fun', got '{:?}'", token);
-                Err(parse_error!(ErrKind::UnexpectedToken, msg, pos))
+                let msg = format!("Expected a declaration such as 'val' or 'fun', got '{:?}'", token);
+                Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(pos)))
             },
             None => {
                 let msg = "Expected end of input after decl";
-                Err(parse_error!(ErrKind::UnexpectedEOF, msg, pos))
+                Err(parse_error!(ErrKind::UnexpectedEOF, msg, self.span_at(pos)))
+            },
+        }
+    }
+
+    /// One function of a `fun` declaration, after the 'fun' or 'and'.
+    fn parse_fun_binding(&mut self) -> Result<FunBinding, ParseError> {
+        let pos = self.pos;
+        let (name, args) = self.parse_fun_head()?;
+        if args.is_empty() {
+            let msg = format!("Expected the arguments of '{}'", name);
+            return Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(self.pos)));
+        }
+        self.parse_clausal_fun(name, args, pos)
+    }
+
+    /// The function name and argument patterns of one clause, up to the
+    /// result type or '='. The name of an infix operator may also stand
+    /// between two patterns, as in `fun xs @ ys = ...`, which takes the pair
+    /// of them as its one argument.
+    fn parse_fun_head(&mut self) -> Result<(String, Vec<AstPattern>), ParseError> {
+        let pos = self.pos;
+        // An infix operator's name may be preceded by 'op'
+        let prefixed = self.peek() == Some(TokenType::Op);
+        if prefixed {
+            self.consume();
+        }
+        let infix_next = match self.tokens.get(self.pos + 1).map(|token| &token.ty) {
+            Some(TokenType::Id(next)) => self.fixity_of(next).is_some(),
+            _ => false,
+        };
+        match self.peek() {
+            Some(TokenType::Id(name)) if prefixed || !infix_next => {
+                self.consume(); // Consume the ID
+                Ok((name, self.parse_fun_args()?))
+            },
+            _ if !prefixed && self.could_start_pattern() => {
+                let left = self.parse_arg_pattern()?;
+                let name = match self.peek() {
+                    Some(TokenType::Id(name)) if self.fixity_of(&name).is_some() => name,
+                    _ => {
+                        let msg = "Expected an infix operator after the left argument";
+                        return Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(self.pos)));
+                    },
+                };
+                self.consume(); // Consume the operator
+                let right = self.parse_arg_pattern()?;
+                let arg = AstPattern::Pair(Box::new(left), Box::new(right), self.span_from(pos));
+                Ok((name, vec![arg]))
+            },
+            _ => {
+                let msg = "Expected identifier after 'fun'";
+                Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(pos)))
+            },
+        }
+    }
+
+    /// One binding of a `val rec`, whose right-hand side must be a `fn`.
+    fn parse_val_rec_binding(&mut self) -> Result<FunBinding, ParseError> {
+        let pos = self.pos;
+        let AstPattern::Id(name, _) = self.parse_atom_pattern()? else {
+            let msg = "Expected a function name after 'val rec'";
+            return Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_from(pos)));
+        };
+        
+        let typ = if self.peek() == Some(TokenType::Colon) {
+            self.consume();
+            Some(self.parse_type_annotation()?)
+        } else {
+            None
+        };
+        
+        self.expect(TokenType::Equal)?;
+        let exp_pos = self.pos;
+        match self.parse_expr()? {
+            AstNode::Fn { clauses, .. } => Ok(FunBinding { name, clauses, typ, span: self.span_from(pos) }),
+            _ => {
+                let msg = format!("The right-hand side of 'val rec {}' must be a 'fn' expression", name);
+                Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_from(exp_pos)))
+            },
+        }
+    }
+
+    /// The rest of a clausal `fun` whose name and first argument patterns
+    /// have been consumed. Each later clause must repeat the name and take
+    /// as many arguments as the first.
+    fn parse_clausal_fun(&mut self, name: String, args: Vec<AstPattern>, pos: usize) -> Result<FunBinding, ParseError> {
+        let arity = args.len();
+        let result = self.parse_result_type(&name)?;
+        let mut clauses = vec![(args, self.parse_expr()?)];
+        
+        while let Some(TokenType::Bar) = self.peek() {
+            self.consume(); // Consume '|'
+            let clause_pos = self.pos;
+            let Some(token) = self.peek() else {
+                let msg = format!("Expected another clause of '{}', got EOF", name);
+                return Err(parse_error!(ErrKind::UnexpectedEOF, msg, self.span_at(clause_pos)));
+            };
+            let (clause_name, args) = self.parse_fun_head()?;
+            if clause_name != name {
+                let msg = format!("Expected another clause of '{}', got '{:?}'", name, token);
+                return Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(clause_pos)));
+            }
+            if args.len() != arity {
+                let msg = format!("Clause of '{}' has {} argument(s), but the first clause has {}",
+                                  name, args.len(), arity);
+                return Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_from(clause_pos)));
+            }
+            match (&result, self.parse_result_type(&name)?) {
+                (Some(first), Some(other)) if first.ty != other.ty => {
+                    let msg = format!("Clause of '{}' has a different result type than the first clause", name);
+                    return Err(parse_error!(ErrKind::UnexpectedToken, msg, other.span));
+                },
+                _ => {},
+            }
+            clauses.push((args, self.parse_expr()?));
+        }
+        
+        let span = self.span_from(pos);
+        // The annotation is of the function's result, so it is completed with
+        // argument types the annotation says nothing about
+        let typ = result.map(|result| TypeAnnotation {
+            ty: (1..=arity).rev().fold(result.ty, |ty, i| {
+                Type::Arrow(Box::new(Type::Var(format!("%{}", i))), Box::new(ty))
+            }),
+            span: result.span,
+        });
+        Ok(FunBinding {
+            name,
+            clauses: curry_clauses(clauses, span),
+            typ,
+            span,
+        })
+    }
+
+    /// The optional `: ty` and the '=' between a clause's arguments and its
+    /// body.
+    fn parse_result_type(&mut self, name: &str) -> Result<Option<TypeAnnotation>, ParseError> {
+        let typ = if self.peek() == Some(TokenType::Colon) {
+            self.consume();
+            Some(self.parse_type_annotation()?)
+        } else {
+            None
+        };
+        if self.peek() == Some(TokenType::FatArrow) {
+            let msg = format!("Expected '=' after the arguments of '{}', clauses of 'fun' are written 'f p = e'", name);
+            return Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(self.pos)));
+        }
+        self.expect(TokenType::Equal)?;
+        Ok(typ)
+    }
+
+    /// The argument patterns of a clausal `fun`, up to the '='.
+    fn parse_fun_args(&mut self) -> Result<Vec<AstPattern>, ParseError> {
+        let mut args = Vec::new();
+        while self.could_start_pattern() {
+            args.push(self.parse_arg_pattern()?);
+        }
+        Ok(args)
+    }
+
+    /// An atomic pattern in which a constructor stands alone: in
+    /// `fun f Leaf x = ...` the 'x' is the next argument, not Leaf's.
+    fn parse_arg_pattern(&mut self) -> Result<AstPattern, ParseError> {
+        let pos = self.pos;
+        let name = match self.peek() {
+            Some(TokenType::Id(id)) if self.is_constructor(&id) => id,
+            Some(TokenType::Some) => "some".to_string(),
+            Some(TokenType::None) => "none".to_string(),
+            _ => return self.parse_atom_pattern(),
+        };
+        self.consume();
+        Ok(AstPattern::Constructor(name, None, self.span_from(pos)))
+    }
+
+    /// The type variables before a datatype's name: none, `'a` or `('a, 'b)`.
+    fn parse_type_params(&mut self) -> Result<Vec<String>, ParseError> {
+        match self.peek() {
+            Some(TokenType::SingleQuote) => Ok(vec![self.parse_type_var()?]),
+            Some(TokenType::LeftParen) => {
+                self.consume(); // Consume '('
+                let mut params = vec![self.parse_type_var()?];
+                while let Some(TokenType::Comma) = self.peek() {
+                    self.consume(); // Consume ','
+                    params.push(self.parse_type_var()?);
+                }
+                self.expect(TokenType::RightParen)?;
+                Ok(params)
+            },
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn parse_type_var(&mut self) -> Result<String, ParseError> {
+        let pos = self.pos;
+        self.expect(TokenType::SingleQuote)?;
+        match self.peek() {
+            Some(TokenType::Id(id)) => {
+                self.consume();
+                Ok(id)
+            },
+            _ => {
+                let msg = "Expected identifier for type variable";
+                Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(pos)))
             },
         }
     }
 
-    fn parse_decls(&mut self) -> Result<Vec<AstNode>, ParseError> {
+    fn parse_constructor_decl(&mut self) -> Result<ConstructorDecl, ParseError> {
         let pos = self.pos;
+        let Some(TokenType::Id(name)) = self.peek() else {
+            let msg = "Expected a constructor name";
+            return Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(pos)));
+        };
+        self.consume();
+        self.scope().constructors.insert(name.clone());
+        
+        let arg = if let Some(TokenType::Of) = self.peek() {
+            self.consume(); // Consume 'of'
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        Ok(ConstructorDecl { name, arg, span: self.span_from(pos) })
+    }
+
+    fn parse_decls(&mut self) -> Vec<AstNode> {
         // Multiple SC separated declarations may be made on the same line
         let mut decls = Vec::new();
         loop {
-            let dec = self.parse_decl()?;
-            decls.push(dec); 
+            let pos = self.pos;
+            match self.parse_decl() {
+                Ok(dec) => decls.push(dec),
+                Err(err) => decls.push(self.recover_decl(err, pos)),
+            }
             
-            // Handle extra declarations
+            // Handle extra declarations, a trailing ';' is allowed
             match self.peek() {
                 Some(TokenType::SemiColon) => {
                     self.consume();
-                    continue
+                    if self.at_decl_start() {
+                        continue
+                    }
+                    break
                 },
                 _ => break 
             } 
         }
-        
-        if decls.is_empty() {
-            return Err(parse_error!(ErrKind::InvalidDeclaration,
-                    "Expected at least one declaration.",
-                    pos));
-        }
-        
-        Ok(decls) 
+        decls
     }
 
     fn parse_pattern(&mut self) -> Result<AstPattern, ParseError> {
+        let pos = self.pos;
+        let head = self.parse_atom_pattern()?;
+        if let Some(TokenType::Cons) = self.peek() {
+            self.consume(); // Consume '::'
+            let tail = self.parse_pattern()?;
+            Ok(AstPattern::Cons(Box::new(head), Box::new(tail), self.span_from(pos)))
+        } else {
+            Ok(head)
+        }
+    }
+
+    fn could_start_pattern(&self) -> bool {
+        matches!(self.peek(),
+            Some(TokenType::Wildcard) |
+            Some(TokenType::Integer(_)) |
+            Some(TokenType::Bool(_)) |
+            Some(TokenType::String(_)) |
+            Some(TokenType::Char(_)) |
+            Some(TokenType::Id(_)) |
+            Some(TokenType::Op) |
+            Some(TokenType::Ref) |
+            Some(TokenType::Nil) |
+            Some(TokenType::Some) |
+            Some(TokenType::None) |
+            Some(TokenType::LeftParen) |
+            Some(TokenType::LeftBracket) |
+            Some(TokenType::LeftBrace))
+    }
+
+    /// The argument, if any, of a constructor pattern whose name has been
+    /// consumed.
+    fn parse_constructor_pattern(&mut self, name: String, pos: usize) -> Result<AstPattern, ParseError> {
+        let arg = if self.could_start_pattern() {
+            Some(Box::new(self.parse_atom_pattern()?))
+        } else {
+            None
+        };
+        Ok(AstPattern::Constructor(name, arg, self.span_from(pos)))
+    }
+
+    fn parse_atom_pattern(&mut self) -> Result<AstPattern, ParseError> {
         let pos = self.pos;
         match self.peek() {
             Some(TokenType::Wildcard) => {
                 self.consume();
-                Ok(AstPattern::Wildcard)
+                Ok(AstPattern::Wildcard(self.span_from(pos)))
             },
             Some(TokenType::LeftParen) => {
                 self.consume();
+                if let Some(TokenType::RightParen) = self.peek() {
+                    self.consume(); // Consume ')'
+                    return Ok(AstPattern::Tuple(Vec::new(), self.span_from(pos)));
+                }
                 let p1 = self.parse_pattern()?;
+                if let Some(TokenType::RightParen) = self.peek() {
+                    self.consume(); // A parenthesised pattern such as '(x :: xs)'
+                    return Ok(p1);
+                }
                 self.expect(TokenType::Comma)?;
                 let p2 = self.parse_pattern()?;
+                if let Some(TokenType::RightParen) = self.peek() {
+                    self.consume(); // Consume ')'
+                    return Ok(AstPattern::Pair(Box::new(p1), Box::new(p2), self.span_from(pos)));
+                }
+                let mut elements = vec![p1, p2];
+                while let Some(TokenType::Comma) = self.peek() {
+                    self.consume(); // Consume ','
+                    elements.push(self.parse_pattern()?);
+                }
                 self.expect(TokenType::RightParen)?;
-                Ok(AstPattern::Pair(Box::new(p1), Box::new(p2)))
+                Ok(AstPattern::Tuple(elements, self.span_from(pos)))
+            },
+            Some(TokenType::LeftBracket) => {
+                self.consume(); // Consume '['
+                let mut elements = Vec::new();
+                if self.peek() != Some(TokenType::RightBracket) {
+                    elements.push(self.parse_pattern()?);
+                    while let Some(TokenType::Comma) = self.peek() {
+                        self.consume(); // Consume ','
+                        elements.push(self.parse_pattern()?);
+                    }
+                }
+                self.expect(TokenType::RightBracket)?;
+                Ok(AstPattern::List(elements, self.span_from(pos)))
+            },
+            Some(TokenType::Nil) => {
+                self.consume();
+                Ok(AstPattern::Nil(self.span_from(pos)))
+            },
+            Some(TokenType::LeftBrace) => {
+                self.consume(); // Consume '{'
+                let (fields, flexible) = self.parse_record_fields(true, |parser, label, label_pos| {
+                    // A field without a pattern, as in '{name, age}', binds its label
+                    let punned = !label.starts_with(|c: char| c.is_ascii_digit());
+                    if punned && parser.peek() != Some(TokenType::Equal) {
+                        return Ok(AstPattern::Id(label.to_string(), parser.span_from(label_pos)));
+                    }
+                    parser.expect(TokenType::Equal)?;
+                    parser.parse_pattern()
+                })?;
+                Ok(AstPattern::Record(fields, flexible, self.span_from(pos)))
+            },
+            Some(TokenType::Ref) => {
+                self.consume(); // Consume 'ref'
+                let inner = self.parse_atom_pattern()?;
+                Ok(AstPattern::Ref(Box::new(inner), self.span_from(pos)))
+            },
+            Some(TokenType::Id(id)) if self.is_constructor(&id) => {
+                self.consume();
+                self.parse_constructor_pattern(id, pos)
+            },
+            Some(TokenType::Some) => {
+                self.consume();
+                self.parse_constructor_pattern("some".to_string(), pos)
+            },
+            Some(TokenType::None) => {
+                self.consume();
+                self.parse_constructor_pattern("none".to_string(), pos)
             },
             Some(TokenType::Id(id)) => {
                 self.consume();
-                Ok(AstPattern::Id(id))
+                Ok(AstPattern::Id(id, self.span_from(pos)))
+            },
+            Some(TokenType::Op) => {
+                self.consume(); // Binding an infix operator, as in 'val op @ = ...'
+                match self.peek() {
+                    Some(TokenType::Id(id)) => {
+                        self.consume();
+                        Ok(AstPattern::Id(id, self.span_from(pos)))
+                    },
+                    _ => {
+                        let msg = "Expected an operator after 'op'";
+                        Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(self.pos)))
+                    },
+                }
+            },
+            Some(TokenType::Integer(i)) => {
+                self.consume();
+                Ok(AstPattern::Literal(LiteralValue::Integer(i), self.span_from(pos)))
+            },
+            Some(TokenType::Bool(b)) => {
+                self.consume();
+                Ok(AstPattern::Literal(LiteralValue::Boolean(b), self.span_from(pos)))
+            },
+            Some(TokenType::String(s)) => {
+                self.consume();
+                Ok(AstPattern::Literal(LiteralValue::String(s), self.span_from(pos)))
             },
-            Some(TokenType::Integer(_)) | 
-            Some(TokenType::Bool(_)) | 
-            Some(TokenType::String(_)) => {
+            Some(TokenType::Char(c)) => {
                 self.consume();
-                Ok(AstPattern::Literal)
+                Ok(AstPattern::Literal(LiteralValue::Char(c), self.span_from(pos)))
             },
             Some(TokenType::SingleQuote) => {
                 self.consume();
                 if let Some(TokenType::Id(id)) = self.peek() {
                     self.consume();
-                    Ok(AstPattern::Var(id))
+                    Ok(AstPattern::Var(id, self.span_from(pos)))
                 } else {
                     Err(parse_error!(ErrKind::UnexpectedToken,
                             "Expected identifier after single quote in pattern",
-                            pos))
+                            self.span_at(pos)))
                 }
             },
             Some(token) => {
                 let msg = format!("Invalid pattern, unexpected token: {:?}", token);
-                Err(parse_error!(ErrKind::UnexpectedToken, msg, pos))
+                Err(parse_error!(ErrKind::UnexpectedToken, msg, self.span_at(pos)))
             },
-            None => Err(parse_error!(ErrKind::UnexpectedToken, "Unexpected EOF", pos)), 
+            None => Err(parse_error!(ErrKind::UnexpectedToken, "Unexpected EOF", self.span_at(pos))), 
         }
     }
 }
 
+/// The fixity of the built-in operators, as in the SML basis.
+fn builtin_fixities() -> HashMap<String, Fixity> {
+    let groups: [(&[&str], u8, Assoc); 5] = [
+        (&["*", "/"], 7, Assoc::Left),
+        (&["+", "-"], 6, Assoc::Left),
+        (&["::"], 5, Assoc::Right),
+        (&["=", "<>", "<", "<=", ">", ">="], 4, Assoc::Left),
+        (&[":="], 3, Assoc::Left),
+    ];
+    let mut fixities = HashMap::new();
+    for (names, prec, assoc) in groups {
+        for name in names {
+            fixities.insert(name.to_string(), Fixity { prec, assoc });
+        }
+    }
+    fixities
+}
+
+/// The name of `token` if it can be an infix operator: an identifier, or
+/// one of the built-in operator tokens.
+fn operator_name(token: &TokenType) -> Option<String> {
+    match token {
+        TokenType::Id(name) => Some(name.clone()),
+        TokenType::Plus | TokenType::Minus | TokenType::Multiply | TokenType::Divide
+        | TokenType::Less | TokenType::LessEqual | TokenType::Greater | TokenType::GreaterEqual
+        | TokenType::Equal | TokenType::NotEqual | TokenType::Cons => Some(token.to_string()),
+        _ => None,
+    }
+}
+
+fn builtin_op(name: &str) -> Option<BinOp> {
+    match name {
+        "+" => Some(BinOp::Add),
+        "-" => Some(BinOp::Sub),
+        "*" => Some(BinOp::Mul),
+        "/" => Some(BinOp::Div),
+        "<" => Some(BinOp::Lt),
+        "<=" => Some(BinOp::Lte),
+        ">" => Some(BinOp::Gt),
+        ">=" => Some(BinOp::Gte),
+        "=" => Some(BinOp::Eq),
+        "<>" => Some(BinOp::Neq),
+        "::" => Some(BinOp::Cons),
+        _ => None,
+    }
+}
+
+fn is_builtin_op(name: &str) -> bool {
+    name == ":=" || builtin_op(name).is_some()
+}
+
+/// `left name right`: a built-in operation, or else the operator applied to
+/// the pair of operands.
+fn infix_node(name: &str, op_span: Span, left: AstNode, right: AstNode, span: Span) -> AstNode {
+    if name == ":=" {
+        return AstNode::Assign {
+            target: Box::new(left),
+            value: Box::new(right),
+            span,
+        };
+    }
+    match builtin_op(name) {
+        Some(op) => AstNode::BinOp {
+            left: Box::new(left),
+            op,
+            right: Box::new(right),
+            span,
+        },
+        None => AstNode::App {
+            func: Box::new(AstNode::Id(name.to_string(), op_span)),
+            arg: Box::new(AstNode::Tuple(vec![Box::new(left), Box::new(right)], span)),
+            span,
+        },
+    }
+}
+
+/// `op +` for a built-in operator, which is `fn (l, r) => l + r`. The names
+/// cannot clash with the user's, as no identifier mixes letters and symbols.
+fn builtin_op_fn(name: &str, span: Span) -> AstNode {
+    let (l, r) = ("%l".to_string(), "%r".to_string());
+    let pattern = AstPattern::Pair(
+        Box::new(AstPattern::Id(l.clone(), span)),
+        Box::new(AstPattern::Id(r.clone(), span)),
+        span,
+    );
+    let body = infix_node(name, span, AstNode::Id(l, span), AstNode::Id(r, span), span);
+    AstNode::Fn { clauses: vec![(pattern, Box::new(body))], span }
+}
+
+/// `~`, which is `fn x => ~x`. Each use is a separate function, so that it
+/// can negate either ints or reals.
+fn builtin_neg_fn(span: Span) -> AstNode {
+    let x = "%x".to_string();
+    let body = AstNode::Neg { expr: Box::new(AstNode::Id(x.clone(), span)), span };
+    AstNode::Fn { clauses: vec![(AstPattern::Id(x, span), Box::new(body))], span }
+}
+
+/// The clauses of a clausal `fun` as single-argument `FunDecl` clauses. With
+/// several arguments `fun f p1 p2 = e | ...` becomes
+/// `fn %1 => fn %2 => case (%1, %2) of (p1, p2) => e | ...`.
+fn curry_clauses(clauses: Vec<(Vec<AstPattern>, AstNode)>, span: Span) -> Vec<(AstPattern, Box<AstNode>)> {
+    let arity = clauses[0].0.len();
+    if arity == 1 {
+        return clauses
+            .into_iter()
+            .map(|(mut args, body)| (args.remove(0), Box::new(body)))
+            .collect();
+    }
+    
+    let params: Vec<String> = (1..=arity).map(|i| format!("%{}", i)).collect();
+    let arms = clauses
+        .into_iter()
+        .map(|(args, body)| {
+            let arm_span = args[0].span().to(body.span());
+            let pattern = match <[AstPattern; 2]>::try_from(args) {
+                Ok([first, second]) => AstPattern::Pair(Box::new(first), Box::new(second), arm_span),
+                Err(args) => AstPattern::Tuple(args, arm_span),
+            };
+            (pattern, Box::new(body))
+        })
+        .collect();
+    let scrutinee = params.iter().map(|param| Box::new(AstNode::Id(param.clone(), span))).collect();
+    
+    let mut body = AstNode::Case {
+        scrutinee: Box::new(AstNode::Tuple(scrutinee, span)),
+        arms,
+        span,
+    };
+    for param in params[1..].iter().rev() {
+        body = AstNode::Fn {
+            clauses: vec![(AstPattern::Id(param.clone(), span), Box::new(body))],
+            span,
+        };
+    }
+    vec![(AstPattern::Id(params[0].clone(), span), Box::new(body))]
+}
@@ -1,2 +1,4 @@
 pub mod visit_debug;
 pub mod visit_def;
+pub mod visit_infer;
+pub mod visit_eval;
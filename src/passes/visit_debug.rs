@@ -1,34 +1,91 @@
-use crate::ast::{AstNode, AstPattern, Type, BinOp, LiteralValue};
+use crate::ast::{AstNode, AstPattern, BinOp, ConstructorDecl, Fixity, FunBinding, LiteralValue, Type, TypeAnnotation};
+use crate::span::Span;
+use crate::diagnostic::Diagnostic;
 use crate::ast_visitor::Visitable;
 
 pub struct DebugVisitor {
     debug_depth: usize,
 }
 
+impl Default for DebugVisitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DebugVisitor {
     pub fn new() -> Self {
         DebugVisitor {
             debug_depth: 0
         } 
     }    
+
+    fn visit_decls(&mut self, decls: &[Box<AstNode>]) -> Result<(), Diagnostic> {
+        for decl in decls {
+            self.visit_node(decl)?;
+        }
+        Ok(())
+    }
 }
 
-impl<T: Default> Visitable<T> for DebugVisitor {
+impl Visitable<()> for DebugVisitor {
      
-    fn visit_literal(&mut self, lit: &LiteralValue) -> Result<T, String> {
+    fn visit_literal(&mut self, lit: &LiteralValue, _span: &Span) -> Result<(), Diagnostic> {
         let (val, ty) = match lit {
             LiteralValue::Integer(s) => (s, "int"),
+            LiteralValue::Real(s) => (s, "real"),
             LiteralValue::Boolean(s) => (s, "bool"),
             LiteralValue::String(s) => (s, "string"),
+            LiteralValue::Char(s) => (s, "char"),
         }; 
         self.debug_depth += 1; 
         println!("{}<literal val=\"{}\" ty=\"{}\" />", 
                  "  ".repeat(self.debug_depth), val, ty);
         self.debug_depth -= 1;
-        Ok(T::default())    
+        Ok(())    
+    }
+
+    fn visit_error(&mut self, _span: &Span) -> Result<(), Diagnostic> {
+        self.debug_depth += 1;
+        println!("{}<error />", "  ".repeat(self.debug_depth));
+        self.debug_depth -= 1;
+        Ok(())
+    }
+
+    fn visit_datatype_decl(&mut self, name: &str, params: &[String], constructors: &[ConstructorDecl],
+                           _span: &Span) -> Result<(), Diagnostic>
+    {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<datatype name=\"{}\" params=\"{}\">", indent, name, params.join(", "));
+        
+        let ctor_indent = " ".repeat(2 * (self.debug_depth + 1));
+        for ctor in constructors {
+            match &ctor.arg {
+                Some(ty) => println!("{}<constructor name=\"{}\" of=\"{}\" />", ctor_indent, ctor.name, ty),
+                None => println!("{}<constructor name=\"{}\" />", ctor_indent, ctor.name),
+            }
+        }
+        
+        println!("{}</datatype>", indent);
+        Ok(())
+    }
+
+    fn visit_exception_decl(&mut self, name: &str, arg: &Option<Type>, _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        match arg {
+            Some(ty) => println!("{}<exception name=\"{}\" of=\"{}\" />", indent, name, ty),
+            None => println!("{}<exception name=\"{}\" />", indent, name),
+        }
+        Ok(())
     }
 
-    fn visit_program(&mut self, stmts: &Vec<Box<AstNode>>) -> Result<T, String> {
+    fn visit_fixity_decl(&mut self, names: &[String], fixity: &Fixity, _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<fixity ops=\"{}\" fixity=\"{}\" />", indent, names.join(" "), fixity);
+        Ok(())
+    }
+
+    fn visit_program(&mut self, stmts: &Vec<Box<AstNode>>, _span: &Span) -> Result<(), Diagnostic> {
         let indent = " ".repeat(2 * self.debug_depth);
         println!("{}<program>", indent);
         
@@ -39,10 +96,10 @@ impl<T: Default> Visitable<T> for DebugVisitor {
         self.debug_depth -= 1;
         
         println!("{}</program>", indent);
-        Ok(T::default())
+        Ok(())
     }
 
-    fn visit_val_decl(&mut self, pat: &AstPattern, typ: &Option<Type>, exp: &AstNode) -> Result<T, String> {
+    fn visit_val_decl(&mut self, pat: &AstPattern, typ: &Option<TypeAnnotation>, exp: &AstNode, _span: &Span) -> Result<(), Diagnostic> {
         let indent = " ".repeat(2 * self.debug_depth);
         println!("{}<val_decl>", indent);
         
@@ -53,11 +110,11 @@ impl<T: Default> Visitable<T> for DebugVisitor {
         self.debug_depth -= 1;
         
         println!("{}</val_decl>", indent);
-        Ok(T::default())
+        Ok(())
     }
 
-    fn visit_fun_decl(&mut self, name: &str, clauses: &Vec<(AstPattern, Box<AstNode>)>, typ: &Option<Type>)
-        -> Result<T, String>
+    fn visit_fun_decl(&mut self, name: &str, clauses: &Vec<(AstPattern, Box<AstNode>)>, typ: &Option<TypeAnnotation>, _span: &Span)
+        -> Result<(), Diagnostic>
     {
         let indent = " ".repeat(2 * self.debug_depth);
         println!("{}<fun_decl name=\"{}\">", indent, name);
@@ -81,10 +138,22 @@ impl<T: Default> Visitable<T> for DebugVisitor {
         self.debug_depth -= 1;
         
         println!("{}</fun_decl>", indent);
-        Ok(T::default())
+        Ok(())
     }
 
-    fn visit_if(&mut self, cond: &AstNode, then: &AstNode, else_: &AstNode) -> Result<T, String> {
+    fn visit_fun_group(&mut self, funs: &[FunBinding], _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<fun_group>", indent);
+        self.debug_depth += 1;
+        for fun in funs {
+            self.visit_fun_decl(&fun.name, &fun.clauses, &fun.typ, &fun.span)?;
+        }
+        self.debug_depth -= 1;
+        println!("{}</fun_group>", indent);
+        Ok(())
+    }
+
+    fn visit_if(&mut self, cond: &AstNode, then: &AstNode, else_: &AstNode, _span: &Span) -> Result<(), Diagnostic> {
         let indent = " ".repeat(2 * self.debug_depth);
         println!("{}<if>", indent);
         
@@ -108,10 +177,10 @@ impl<T: Default> Visitable<T> for DebugVisitor {
         self.debug_depth -= 1;
         
         println!("{}</if>", indent);
-        Ok(T::default())
+        Ok(())
     }
 
-    fn visit_let(&mut self, decl: &AstNode, body: &AstNode) -> Result<T, String> {
+    fn visit_let(&mut self, decls: &[Box<AstNode>], body: &AstNode, _span: &Span) -> Result<(), Diagnostic> {
         let indent = " ".repeat(2 * self.debug_depth);
         println!("{}<let>", indent);
         
@@ -119,7 +188,7 @@ impl<T: Default> Visitable<T> for DebugVisitor {
         
         let decl_indent = " ".repeat(2 * self.debug_depth);
         println!("{}<decl>", decl_indent);
-        self.visit_node(decl)?;
+        self.visit_decls(decls)?;
         println!("{}</decl>", decl_indent);
         
         let body_indent = " ".repeat(2 * self.debug_depth);
@@ -130,10 +199,30 @@ impl<T: Default> Visitable<T> for DebugVisitor {
         self.debug_depth -= 1;
         
         println!("{}</let>", indent);
-        Ok(T::default())
+        Ok(())
     }
 
-    fn visit_fn(&mut self, clauses: &Vec<(AstPattern, Box<AstNode>)>) -> Result<T, String> {
+    fn visit_local(&mut self, private: &[Box<AstNode>], public: &[Box<AstNode>], _span: &Span)
+        -> Result<(), Diagnostic>
+    {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<local>", indent);
+        
+        self.debug_depth += 1;
+        let part_indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<private>", part_indent);
+        self.visit_decls(private)?;
+        println!("{}</private>", part_indent);
+        println!("{}<public>", part_indent);
+        self.visit_decls(public)?;
+        println!("{}</public>", part_indent);
+        self.debug_depth -= 1;
+        
+        println!("{}</local>", indent);
+        Ok(())
+    }
+
+    fn visit_fn(&mut self, clauses: &Vec<(AstPattern, Box<AstNode>)>, _span: &Span) -> Result<(), Diagnostic> {
         let indent = " ".repeat(2 * self.debug_depth);
         println!("{}<fn>", indent);
         
@@ -156,10 +245,37 @@ impl<T: Default> Visitable<T> for DebugVisitor {
         self.debug_depth -= 1;
         
         println!("{}</fn>", indent);
-        Ok(T::default())
+        Ok(())
+    }
+
+    fn visit_case(&mut self, scrutinee: &AstNode, arms: &Vec<(AstPattern, Box<AstNode>)>, _span: &Span)
+        -> Result<(), Diagnostic>
+    {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<case>", indent);
+
+        self.debug_depth += 1;
+        self.visit_node(scrutinee)?;
+
+        for (pat, body) in arms {
+            let arm_indent = " ".repeat(2 * self.debug_depth);
+            println!("{}<arm>", arm_indent);
+
+            self.debug_depth += 1;
+            self.visit_pattern(pat)?;
+            self.visit_node(body)?;
+            self.debug_depth -= 1;
+
+            println!("{}</arm>", arm_indent);
+        }
+
+        self.debug_depth -= 1;
+
+        println!("{}</case>", indent);
+        Ok(())
     }
 
-    fn visit_bin_op(&mut self, left: &AstNode, op: &BinOp, right: &AstNode) -> Result<T, String> {
+    fn visit_bin_op(&mut self, left: &AstNode, op: &BinOp, right: &AstNode, _span: &Span) -> Result<(), Diagnostic> {
         let indent = " ".repeat(2 * self.debug_depth);
         println!("{}<bin_op op=\"{:?}\">", indent, op);
         
@@ -178,10 +294,139 @@ impl<T: Default> Visitable<T> for DebugVisitor {
         self.debug_depth -= 1;
         
         println!("{}</bin_op>", indent);
-        Ok(T::default())
+        Ok(())
+    }
+
+    fn visit_seq(&mut self, exprs: &[Box<AstNode>], _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<seq>", indent);
+        
+        self.debug_depth += 1;
+        for expr in exprs {
+            self.visit_node(expr)?;
+        }
+        self.debug_depth -= 1;
+        
+        println!("{}</seq>", indent);
+        Ok(())
+    }
+
+    fn visit_raise(&mut self, expr: &AstNode, _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<raise>", indent);
+        
+        self.debug_depth += 1;
+        self.visit_node(expr)?;
+        self.debug_depth -= 1;
+        
+        println!("{}</raise>", indent);
+        Ok(())
+    }
+
+    fn visit_handle(&mut self, expr: &AstNode, arms: &Vec<(AstPattern, Box<AstNode>)>, _span: &Span)
+        -> Result<(), Diagnostic>
+    {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<handle>", indent);
+
+        self.debug_depth += 1;
+        self.visit_node(expr)?;
+
+        for (pat, body) in arms {
+            let arm_indent = " ".repeat(2 * self.debug_depth);
+            println!("{}<arm>", arm_indent);
+
+            self.debug_depth += 1;
+            self.visit_pattern(pat)?;
+            self.visit_node(body)?;
+            self.debug_depth -= 1;
+
+            println!("{}</arm>", arm_indent);
+        }
+
+        self.debug_depth -= 1;
+
+        println!("{}</handle>", indent);
+        Ok(())
+    }
+
+    fn visit_ref(&mut self, expr: &AstNode, _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<ref>", indent);
+        
+        self.debug_depth += 1;
+        self.visit_node(expr)?;
+        self.debug_depth -= 1;
+        
+        println!("{}</ref>", indent);
+        Ok(())
+    }
+
+    fn visit_deref(&mut self, expr: &AstNode, _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<deref>", indent);
+        
+        self.debug_depth += 1;
+        self.visit_node(expr)?;
+        self.debug_depth -= 1;
+        
+        println!("{}</deref>", indent);
+        Ok(())
+    }
+
+    fn visit_assign(&mut self, target: &AstNode, value: &AstNode, _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<assign>", indent);
+        
+        self.debug_depth += 1;
+        self.visit_node(target)?;
+        self.visit_node(value)?;
+        self.debug_depth -= 1;
+        
+        println!("{}</assign>", indent);
+        Ok(())
+    }
+
+    fn visit_neg(&mut self, expr: &AstNode, _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<neg>", indent);
+        
+        self.debug_depth += 1;
+        self.visit_node(expr)?;
+        self.debug_depth -= 1;
+        
+        println!("{}</neg>", indent);
+        Ok(())
+    }
+
+
+    fn visit_andalso(&mut self, left: &AstNode, right: &AstNode, _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<andalso>", indent);
+        
+        self.debug_depth += 1;
+        self.visit_node(left)?;
+        self.visit_node(right)?;
+        self.debug_depth -= 1;
+        
+        println!("{}</andalso>", indent);
+        Ok(())
     }
 
-    fn visit_app(&mut self, func: &AstNode, arg: &AstNode) -> Result<T, String> {
+    fn visit_orelse(&mut self, left: &AstNode, right: &AstNode, _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<orelse>", indent);
+        
+        self.debug_depth += 1;
+        self.visit_node(left)?;
+        self.visit_node(right)?;
+        self.debug_depth -= 1;
+        
+        println!("{}</orelse>", indent);
+        Ok(())
+    }
+
+    fn visit_app(&mut self, func: &AstNode, arg: &AstNode, _span: &Span) -> Result<(), Diagnostic> {
         let indent = " ".repeat(2 * self.debug_depth);
         println!("{}<app>", indent);
         
@@ -200,24 +445,24 @@ impl<T: Default> Visitable<T> for DebugVisitor {
         self.debug_depth -= 1;
         
         println!("{}</app>", indent);
-        Ok(T::default())
+        Ok(())
     }
 
-    fn visit_id(&mut self, name: &str) -> Result<T, String> {
+    fn visit_id(&mut self, name: &str, _span: &Span) -> Result<(), Diagnostic> {
         let indent = " ".repeat(2 * self.debug_depth);
         println!("{}<id value=\"{}\">", indent, name);
         println!("{}</id>", indent);
-        Ok(T::default())
+        Ok(())
     }
 
-    fn visit_var(&mut self, name: &str) -> Result<T, String> {
+    fn visit_var(&mut self, name: &str, _span: &Span) -> Result<(), Diagnostic> {
         let indent = " ".repeat(2 * self.debug_depth);
         println!("{}<var value=\"{}\">", indent, name);
         println!("{}</var>", indent);
-        Ok(T::default())
+        Ok(())
     }
 
-    fn visit_tuple(&mut self, elements: &Vec<Box<AstNode>>) -> Result<T, String> {
+    fn visit_tuple(&mut self, elements: &Vec<Box<AstNode>>, _span: &Span) -> Result<(), Diagnostic> {
         let indent = " ".repeat(2 * self.debug_depth);
         println!("{}<tuple size=\"{}\">", indent, elements.len());
         
@@ -228,10 +473,35 @@ impl<T: Default> Visitable<T> for DebugVisitor {
         self.debug_depth -= 1;
         
         println!("{}</tuple>", indent);
-        Ok(T::default())
+        Ok(())
     }
 
-    fn visit_list(&mut self, elements: &Vec<Box<AstNode>>) -> Result<T, String> {
+    fn visit_record(&mut self, fields: &[(String, Box<AstNode>)], _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<record size=\"{}\">", indent, fields.len());
+        
+        self.debug_depth += 1;
+        let field_indent = " ".repeat(2 * self.debug_depth);
+        for (label, expr) in fields {
+            println!("{}<field label=\"{}\">", field_indent, label);
+            self.debug_depth += 1;
+            self.visit_node(expr)?;
+            self.debug_depth -= 1;
+            println!("{}</field>", field_indent);
+        }
+        self.debug_depth -= 1;
+        
+        println!("{}</record>", indent);
+        Ok(())
+    }
+
+    fn visit_selector(&mut self, label: &str, _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<selector label=\"{}\" />", indent, label);
+        Ok(())
+    }
+
+    fn visit_list(&mut self, elements: &Vec<Box<AstNode>>, _span: &Span) -> Result<(), Diagnostic> {
         let indent = " ".repeat(2 * self.debug_depth);
         println!("{}<list size=\"{}\">", indent, elements.len());
         
@@ -241,48 +511,61 @@ impl<T: Default> Visitable<T> for DebugVisitor {
         } 
         self.debug_depth -= 1; 
         println!("{}</list>", indent);
-        Ok(T::default())
+        Ok(())
     }
 
-    fn visit_pattern(&mut self, pat: &AstPattern) -> Result<T, String> {
+    fn visit_pattern(&mut self, pat: &AstPattern) -> Result<(), Diagnostic> {
         match pat {
-            AstPattern::Literal => self.visit_literal_pattern(),
-            AstPattern::Id(name) => self.visit_id_pattern(name),
-            AstPattern::Wildcard => self.visit_wildcard_pattern(),
-            AstPattern::Var(name) => self.visit_var_pattern(name),
-            AstPattern::Pair(first, second) => self.visit_pair_pattern(first, second),
+            AstPattern::Literal(lit, span) => self.visit_literal_pattern(lit, span),
+            AstPattern::Id(name, span) => self.visit_id_pattern(name, span),
+            AstPattern::Wildcard(span) => self.visit_wildcard_pattern(span),
+            AstPattern::Var(name, span) => self.visit_var_pattern(name, span),
+            AstPattern::Pair(first, second, span) => self.visit_pair_pattern(first, second, span),
+            AstPattern::Tuple(elements, span) => self.visit_tuple_pattern(elements, span),
+            AstPattern::Cons(head, tail, span) => self.visit_cons_pattern(head, tail, span),
+            AstPattern::Nil(span) => self.visit_nil_pattern(span),
+            AstPattern::List(elements, span) => self.visit_list_pattern(elements, span),
+            AstPattern::Constructor(name, arg, span) => self.visit_constructor_pattern(name, arg, span),
+            AstPattern::Ref(inner, span) => self.visit_ref_pattern(inner, span),
+            AstPattern::Record(fields, flexible, span) => self.visit_record_pattern(fields, *flexible, span),
         }
     }
 
-    fn visit_literal_pattern(&mut self) -> Result<T, String> {
+    fn visit_literal_pattern(&mut self, lit: &LiteralValue, _span: &Span) -> Result<(), Diagnostic> {
+        let (val, ty) = match lit {
+            LiteralValue::Integer(s) => (s, "int"),
+            LiteralValue::Real(s) => (s, "real"),
+            LiteralValue::Boolean(s) => (s, "bool"),
+            LiteralValue::String(s) => (s, "string"),
+            LiteralValue::Char(s) => (s, "char"),
+        };
         let indent = " ".repeat(2 * self.debug_depth);
-        println!("{}<literal_pattern>", indent);
-        println!("{}</literal_pattern>", indent);
-        Ok(T::default())
+        println!("{}<literal_pattern val=\"{}\" ty=\"{}\" />", indent, val, ty);
+        Ok(())
     }
 
-    fn visit_id_pattern(&mut self, name: &str) -> Result<T, String> {
+    fn visit_id_pattern(&mut self, name: &str, _span: &Span) -> Result<(), Diagnostic> {
         let indent = " ".repeat(2 * self.debug_depth);
         println!("{}<id_pattern value=\"{}\">", indent, name);
         println!("{}</id_pattern>", indent);
-        Ok(T::default())
+        Ok(())
     }
 
-    fn visit_wildcard_pattern(&mut self) -> Result<T, String> {
+    fn visit_wildcard_pattern(&mut self, _span: &Span) -> Result<(), Diagnostic> {
         let indent = " ".repeat(2 * self.debug_depth);
         println!("{}<wildcard_pattern>", indent);
         println!("{}</wildcard_pattern>", indent);
-        Ok(T::default())
+        Ok(())
     }
 
-    fn visit_var_pattern(&mut self, name: &str) -> Result<T, String> {
+    fn visit_var_pattern(&mut self, name: &str, _span: &Span) -> Result<(), Diagnostic> {
         let indent = " ".repeat(2 * self.debug_depth);
         println!("{}<var_pattern value=\"{}\">", indent, name);
         println!("{}</var_pattern>", indent);
-        Ok(T::default())
+        Ok(())
     }
 
-    fn visit_pair_pattern(&mut self, first: &AstPattern, second: &AstPattern) -> Result<T, String> {
+    fn visit_pair_pattern(&mut self, first: &AstPattern, second: &AstPattern, _span: &Span) -> Result<(), Diagnostic> {
         let indent = " ".repeat(2 * self.debug_depth);
         println!("{}<pair_pattern>", indent);
         
@@ -292,20 +575,116 @@ impl<T: Default> Visitable<T> for DebugVisitor {
         self.debug_depth -= 1;
         
         println!("{}</pair_pattern>", indent);
-        Ok(T::default())
+        Ok(())
+    }
+
+    fn visit_tuple_pattern(&mut self, elements: &Vec<AstPattern>, _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<tuple_pattern>", indent);
+        
+        self.debug_depth += 1; 
+        for element in elements {
+            self.visit_pattern(element)?;
+        }
+        self.debug_depth -= 1;
+        
+        println!("{}</tuple_pattern>", indent);
+        Ok(())
+    }
+
+    fn visit_cons_pattern(&mut self, head: &AstPattern, tail: &AstPattern, _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<cons_pattern>", indent);
+        
+        self.debug_depth += 1; 
+        self.visit_pattern(head)?;
+        self.visit_pattern(tail)?; 
+        self.debug_depth -= 1;
+        
+        println!("{}</cons_pattern>", indent);
+        Ok(())
+    }
+
+    fn visit_nil_pattern(&mut self, _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<nil_pattern />", indent);
+        Ok(())
+    }
+
+    fn visit_list_pattern(&mut self, elements: &Vec<AstPattern>, _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<list_pattern>", indent);
+        
+        self.debug_depth += 1; 
+        for element in elements {
+            self.visit_pattern(element)?;
+        }
+        self.debug_depth -= 1;
+        
+        println!("{}</list_pattern>", indent);
+        Ok(())
+    }
+
+    fn visit_ref_pattern(&mut self, inner: &AstPattern, _span: &Span) -> Result<(), Diagnostic> {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<ref_pattern>", indent);
+        
+        self.debug_depth += 1;
+        self.visit_pattern(inner)?;
+        self.debug_depth -= 1;
+        
+        println!("{}</ref_pattern>", indent);
+        Ok(())
+    }
+
+    fn visit_record_pattern(&mut self, fields: &[(String, AstPattern)], flexible: bool, _span: &Span)
+        -> Result<(), Diagnostic>
+    {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<record_pattern flexible=\"{}\">", indent, flexible);
+        
+        self.debug_depth += 1;
+        let field_indent = " ".repeat(2 * self.debug_depth);
+        for (label, pat) in fields {
+            println!("{}<field label=\"{}\">", field_indent, label);
+            self.debug_depth += 1;
+            self.visit_pattern(pat)?;
+            self.debug_depth -= 1;
+            println!("{}</field>", field_indent);
+        }
+        self.debug_depth -= 1;
+        
+        println!("{}</record_pattern>", indent);
+        Ok(())
+    }
+
+    fn visit_constructor_pattern(&mut self, name: &str, arg: &Option<Box<AstPattern>>, _span: &Span)
+        -> Result<(), Diagnostic>
+    {
+        let indent = " ".repeat(2 * self.debug_depth);
+        println!("{}<constructor_pattern name=\"{}\">", indent, name);
+        
+        self.debug_depth += 1; 
+        if let Some(arg) = arg {
+            self.visit_pattern(arg)?;
+        }
+        self.debug_depth -= 1;
+        
+        println!("{}</constructor_pattern>", indent);
+        Ok(())
     }
 
-    fn visit_type(&mut self, typ: &Option<Type>) -> Result<T, String> {
+    fn visit_type(&mut self, typ: &Option<TypeAnnotation>) -> Result<(), Diagnostic> {
         let indent = " ".repeat(2 * self.debug_depth);
         
         if let Some(t) = typ {
-            println!("{}<type value=\"{:?}\">", indent, t);
+            println!("{}<type value=\"{:?}\">", indent, t.ty);
             println!("{}</type>", indent);
         } else {
             println!("{}<type value=\"none\">", indent);
             println!("{}</type>", indent);
         }   
-        Ok(T::default())
+        Ok(())
     } 
 }
 
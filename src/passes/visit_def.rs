@@ -1,21 +1,96 @@
+use std::collections::HashSet;
+use crate::ast::{AstNode, ConstructorDecl, Type};
 use crate::ast_visitor::Visitable;
+use crate::span::Span;
+use crate::diagnostic::Diagnostic;
+use crate::symtab::SymbolTable;
 
-pub struct DefVisitor {}
+pub struct DefVisitor {
+    symtab: SymbolTable,
+}
+
+impl Default for DefVisitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl DefVisitor {
     pub fn new() -> Self {
-        DefVisitor {}
+        let mut visitor = DefVisitor {
+            symtab: SymbolTable::new(),
+        };
+        visitor.visit(&AstNode::option_datatype()).expect("the option datatype is well formed");
+        visitor.visit(&AstNode::builtin_exceptions()).expect("the built-in exceptions are well formed");
+        visitor.visit(&AstNode::builtin_functions()).expect("the built-in functions are well formed");
+        visitor
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symtab
     }
 }
 
-impl<T: Default> Visitable<T> for DefVisitor {
+impl Visitable<()> for DefVisitor {
     
-    fn visit_val_decl(&mut self, pat: &crate::ast::AstPattern, typ: &Option<crate::ast::Type>, exp: &crate::ast::AstNode)
-            -> Result<T, String> {
+    fn visit_val_decl(&mut self, _pat: &crate::ast::AstPattern, _typ: &Option<crate::ast::TypeAnnotation>, _exp: &crate::ast::AstNode,
+                      _span: &Span)
+            -> Result<(), Diagnostic> {
+
+        Ok(())
+    }
 
-        println!("Visit val decl!");
+    fn visit_datatype_decl(&mut self, name: &str, params: &[String], constructors: &[ConstructorDecl],
+                           _span: &Span) -> Result<(), Diagnostic>
+    {
+        let result = Type::Con(name.to_string(), params.iter().cloned().map(Type::Var).collect());
+        let mut seen = HashSet::new();
+        for ctor in constructors {
+            if !seen.insert(ctor.name.as_str()) {
+                let msg = format!("Constructor '{}' is declared more than once in datatype '{}'", ctor.name, name);
+                return Err(Diagnostic::error(msg).with_primary(ctor.span, "declared again here"));
+            }
+            let ty = match &ctor.arg {
+                Some(arg) => Type::Arrow(Box::new(arg.clone()), Box::new(result.clone())),
+                None => result.clone(),
+            };
+            self.symtab.define(&ctor.name, ty);
+        }
+        Ok(())
+    }
 
-        Ok(T::default())
+    fn visit_exception_decl(&mut self, name: &str, arg: &Option<Type>, _span: &Span) -> Result<(), Diagnostic> {
+        let exn = Type::Con("exn".to_string(), Vec::new());
+        let ty = match arg {
+            Some(arg) => Type::Arrow(Box::new(arg.clone()), Box::new(exn)),
+            None => exn,
+        };
+        self.symtab.define(name, ty);
+        Ok(())
     }
-}  
 
+    fn visit_let(&mut self, decls: &[Box<AstNode>], body: &AstNode, _span: &Span) -> Result<(), Diagnostic> {
+        self.symtab.enter_scope();
+        let result = decls
+            .iter()
+            .try_for_each(|decl| self.visit_node(decl))
+            .and_then(|_| self.visit_node(body));
+        self.symtab.pop_scope();
+        result
+    }
+
+    fn visit_local(&mut self, private: &[Box<AstNode>], public: &[Box<AstNode>], _span: &Span)
+        -> Result<(), Diagnostic>
+    {
+        // The private part gets a scope of its own, while the public part is
+        // defined in the enclosing scope again, on top of the private one
+        let outer = self.symtab.current_scope();
+        self.symtab.enter_scope();
+        let result = private.iter().try_for_each(|decl| self.visit_node(decl));
+        self.symtab.push_scope(outer);
+        let result = result.and_then(|_| public.iter().try_for_each(|decl| self.visit_node(decl)));
+        self.symtab.pop_scope();
+        self.symtab.pop_scope();
+        result
+    }
+}  
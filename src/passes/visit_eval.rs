@@ -0,0 +1,621 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::rc::Rc;
+use crate::ast::{AstNode, AstPattern, ConstructorDecl, FunBinding, Type, TypeAnnotation, BinOp, LiteralValue};
+use crate::ast_visitor::Visitable;
+use crate::span::Span;
+use crate::diagnostic::Diagnostic;
+use crate::value::{Closure, Env, Exception, Value};
+
+/// Stack space that nested calls may use by default, which fits in the
+/// stack of any thread. `main` runs the interpreter on a larger one.
+const DEFAULT_STACK_LIMIT: usize = 1 << 20;
+
+/// Tree-walking interpreter. Each visit evaluates the node in the current
+/// environment and returns its value; declarations extend the environment.
+pub struct EvalVisitor {
+    env: Env,
+    /// number of enclosing `let` expressions, zero for top-level declarations.
+    let_depth: usize,
+    /// values of the top-level bindings made since `take_bindings` was
+    /// last called, in declaration order.
+    bindings: Vec<(String, Value)>,
+    /// the exception being raised while its error unwinds to a `handle`.
+    raised: Option<Value>,
+    /// the environment declaring the built-in exceptions, which the
+    /// interpreter raises even where their names are shadowed.
+    builtins: Env,
+    /// stack address where evaluation started, and how far below it calls
+    /// may go before they are stopped with an error rather than overflowing
+    /// the stack.
+    stack_base: usize,
+    stack_limit: usize,
+}
+
+impl Default for EvalVisitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EvalVisitor {
+    pub fn new() -> Self {
+        Self::with_stack_limit(DEFAULT_STACK_LIMIT)
+    }
+
+    /// An interpreter whose calls may use up to `stack_limit` bytes of the
+    /// stack of the thread it is created on.
+    pub fn with_stack_limit(stack_limit: usize) -> Self {
+        let mut visitor = EvalVisitor {
+            env: Env::new(),
+            let_depth: 0,
+            bindings: Vec::new(),
+            raised: None,
+            builtins: Env::new(),
+            stack_base: stack_address(),
+            stack_limit,
+        };
+        visitor.visit(&AstNode::option_datatype()).expect("the option datatype is well formed");
+        visitor.visit(&AstNode::builtin_exceptions()).expect("the built-in exceptions are well formed");
+        visitor.builtins = visitor.env.clone();
+        visitor.visit(&AstNode::builtin_functions()).expect("the built-in functions are well formed");
+        // Only the program's own bindings are shown
+        visitor.bindings.clear();
+        visitor
+    }
+
+    /// The top-level bindings made since the last call, so they can be shown
+    /// as each declaration is run.
+    pub fn take_bindings(&mut self) -> Vec<(String, Value)> {
+        std::mem::take(&mut self.bindings)
+    }
+
+    /// Start raising `exn`. The returned error unwinds the evaluation up to
+    /// the nearest `handle`, and is reported as it is if nothing catches it.
+    fn raise(&mut self, exn: Value, label: &str, span: &Span) -> Diagnostic {
+        let err = Diagnostic::error(format!("Uncaught exception {}", exn)).with_primary(*span, label);
+        self.raised = Some(exn);
+        err
+    }
+
+    /// Raise the built-in exception `name`, such as `Match`.
+    fn raise_builtin(&mut self, name: &str, label: String, span: &Span) -> Diagnostic {
+        let exn = self.builtins.lookup(name).expect("built-in exceptions are always declared");
+        self.raise(exn, &label, span)
+    }
+
+    /// Evaluate `node` in `env`, restoring the current environment afterwards
+    /// even if evaluation fails.
+    fn eval_in(&mut self, env: Env, node: &AstNode) -> Result<Value, Diagnostic> {
+        let saved = std::mem::replace(&mut self.env, env);
+        let result = self.visit_node(node);
+        self.env = saved;
+        result
+    }
+
+    fn apply(&mut self, func: Value, arg: Value, span: &Span) -> Result<Value, Diagnostic> {
+        let closure = match func {
+            Value::Closure(closure) => closure,
+            Value::Constructor(name) => return Ok(Value::Data(name, Some(Box::new(arg)))),
+            Value::ExnConstructor(exn) => return Ok(Value::Exn(exn, Some(Box::new(arg)))),
+            Value::Selector(label) => {
+                return arg.field(&label)
+                    .cloned()
+                    .ok_or_else(|| runtime_error(format!("{} has no field '{}'", arg, label), span));
+            },
+            other => return Err(runtime_error(format!("Can not apply non-function value {}", other), span)),
+        };
+        if stack_address().abs_diff(self.stack_base) > self.stack_limit {
+            return Err(runtime_error("Stack overflow: function calls are nested too deeply".to_string(), span));
+        }
+        match self.eval_arms(&closure.clauses, &closure.env, &arg)? {
+            Some(value) => Ok(value),
+            None => Err(self.raise_builtin("Match", format!("no clause matches {}", arg), span)),
+        }
+    }
+
+    /// Evaluate the body of the first arm whose pattern matches `value`, in
+    /// `env` extended with the pattern's variables. `None` if no arm matches.
+    fn eval_arms(&mut self, arms: &[(AstPattern, Box<AstNode>)], env: &Env, value: &Value)
+        -> Result<Option<Value>, Diagnostic>
+    {
+        for (pat, body) in arms {
+            let mut bound = Vec::new();
+            if match_pattern(pat, value, env, &mut bound).map_err(|e| runtime_error(e, pat.span()))? {
+                let env = env.extend();
+                for (name, value) in bound {
+                    env.define(&name, value);
+                }
+                return self.eval_in(env, body).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn eval_bool(&mut self, node: &AstNode) -> Result<bool, Diagnostic> {
+        match self.visit_node(node)? {
+            Value::Bool(b) => Ok(b),
+            other => Err(runtime_error(format!("Expected a boolean, got {}", other), node.span())),
+        }
+    }
+
+    fn define_all(&mut self, bound: Vec<(String, Value)>) {
+        for (name, value) in bound {
+            self.env.define(&name, value.clone());
+            if self.let_depth == 0 {
+                self.bindings.push((name, value));
+            }
+        }
+    }
+}
+
+impl Visitable<Value> for EvalVisitor {
+
+    fn visit_program(&mut self, stmts: &Vec<Box<AstNode>>, _span: &Span) -> Result<Value, Diagnostic> {
+        for stmt in stmts {
+            self.visit_node(stmt)?;
+        }
+        Ok(Value::Unit)
+    }
+
+    fn visit_val_decl(&mut self, pat: &AstPattern, _typ: &Option<TypeAnnotation>, exp: &AstNode,
+                      span: &Span) -> Result<Value, Diagnostic>
+    {
+        let value = self.visit_node(exp)?;
+        let mut bound = Vec::new();
+        if !match_pattern(pat, &value, &self.env, &mut bound).map_err(|e| runtime_error(e, pat.span()))? {
+            return Err(self.raise_builtin("Bind", format!("pattern does not match {}", value), span));
+        }
+        self.env = self.env.extend();
+        self.define_all(bound);
+        Ok(Value::Unit)
+    }
+
+    fn visit_exception_decl(&mut self, name: &str, arg: &Option<Type>, _span: &Span) -> Result<Value, Diagnostic> {
+        let exn = Rc::new(Exception { name: name.to_string() });
+        let value = match arg {
+            Some(_) => Value::ExnConstructor(exn),
+            None => Value::Exn(exn, None),
+        };
+        self.env = self.env.extend();
+        self.env.define(name, value);
+        Ok(Value::Unit)
+    }
+
+    fn visit_datatype_decl(&mut self, _name: &str, _params: &[String], constructors: &[ConstructorDecl],
+                           _span: &Span) -> Result<Value, Diagnostic>
+    {
+        self.env = self.env.extend();
+        for ctor in constructors {
+            let value = match ctor.arg {
+                Some(_) => Value::Constructor(ctor.name.clone()),
+                None => Value::Data(ctor.name.clone(), None),
+            };
+            self.env.define(&ctor.name, value);
+        }
+        Ok(Value::Unit)
+    }
+
+    fn visit_fun_decl(
+        &mut self, name: &str,
+        clauses: &Vec<(AstPattern, Box<AstNode>)>,
+        _typ: &Option<TypeAnnotation>,
+        _span: &Span) -> Result<Value, Diagnostic>
+    {
+        // The closure captures the frame it is bound in, so it can call itself
+        self.env = self.env.extend();
+        let closure = Value::Closure(Rc::new(Closure {
+            clauses: clauses.clone(),
+            env: self.env.clone(),
+        }));
+        self.define_all(vec![(name.to_string(), closure)]);
+        Ok(Value::Unit)
+    }
+
+    fn visit_fun_group(&mut self, funs: &[FunBinding], _span: &Span) -> Result<Value, Diagnostic> {
+        // All closures share the frame they are bound in, so each can call the others
+        self.env = self.env.extend();
+        let closures = funs
+            .iter()
+            .map(|fun| {
+                let closure = Value::Closure(Rc::new(Closure {
+                    clauses: fun.clauses.clone(),
+                    env: self.env.clone(),
+                }));
+                (fun.name.clone(), closure)
+            })
+            .collect();
+        self.define_all(closures);
+        Ok(Value::Unit)
+    }
+
+    fn visit_if(&mut self, cond: &AstNode, then: &AstNode, else_: &AstNode, _span: &Span)
+        -> Result<Value, Diagnostic>
+    {
+        match self.visit_node(cond)? {
+            Value::Bool(true) => self.visit_node(then),
+            Value::Bool(false) => self.visit_node(else_),
+            other => Err(runtime_error(format!("Condition must be a boolean, got {}", other), cond.span())),
+        }
+    }
+
+    fn visit_let(&mut self, decls: &[Box<AstNode>], body: &AstNode, _span: &Span) -> Result<Value, Diagnostic> {
+        let saved = self.env.clone();
+        self.let_depth += 1;
+        let result = decls
+            .iter()
+            .try_for_each(|decl| self.visit_node(decl).map(drop))
+            .and_then(|_| self.visit_node(body));
+        self.let_depth -= 1;
+        self.env = saved;
+        result
+    }
+
+    fn visit_local(&mut self, private: &[Box<AstNode>], public: &[Box<AstNode>], _span: &Span)
+        -> Result<Value, Diagnostic>
+    {
+        let saved = self.env.clone();
+        self.let_depth += 1;
+        let result = private.iter().try_for_each(|decl| self.visit_node(decl).map(drop));
+        self.let_depth -= 1;
+        let private_env = self.env.clone();
+        let result = result.and_then(|_| public.iter().try_for_each(|decl| self.visit_node(decl).map(drop)));
+        let exported = self.env.defined_since(&private_env);
+        
+        // Only the public bindings stay in scope
+        self.env = saved.extend();
+        for (name, value) in exported {
+            self.env.define(&name, value);
+        }
+        result.map(|_| Value::Unit)
+    }
+
+    fn visit_fn(&mut self, clauses: &Vec<(AstPattern, Box<AstNode>)>, _span: &Span)
+        -> Result<Value, Diagnostic>
+    {
+        Ok(Value::Closure(Rc::new(Closure {
+            clauses: clauses.clone(),
+            env: self.env.clone(),
+        })))
+    }
+
+    fn visit_case(&mut self, scrutinee: &AstNode, arms: &Vec<(AstPattern, Box<AstNode>)>, span: &Span)
+        -> Result<Value, Diagnostic>
+    {
+        let value = self.visit_node(scrutinee)?;
+        let env = self.env.clone();
+        match self.eval_arms(arms, &env, &value)? {
+            Some(result) => Ok(result),
+            None => Err(self.raise_builtin("Match", format!("no case arm matches {}", value), span)),
+        }
+    }
+
+    fn visit_bin_op(&mut self, left: &AstNode, op: &BinOp, right: &AstNode, span: &Span)
+        -> Result<Value, Diagnostic>
+    {
+        let lhs = self.visit_node(left)?;
+        let rhs = self.visit_node(right)?;
+        let at = |msg: String| runtime_error(msg, span);
+        match op {
+            BinOp::Eq => return Ok(Value::Bool(lhs.equals(&rhs))),
+            BinOp::Neq => return Ok(Value::Bool(!lhs.equals(&rhs))),
+            BinOp::Cons => {
+                let Value::List(mut values) = rhs else {
+                    return Err(at(format!("Can not cons onto non-list value {}", rhs)));
+                };
+                values.insert(0, lhs);
+                return Ok(Value::List(values));
+            },
+            _ => {},
+        }
+
+        let (a, b) = match (&lhs, &rhs) {
+            (Value::Int(a), Value::Int(b)) => (*a, *b),
+            (Value::Real(a), Value::Real(b)) => return Ok(real_bin_op(op, *a, *b)),
+            (Value::String(a), Value::String(b)) => return Ok(compare(op, a.cmp(b))),
+            (Value::Char(a), Value::Char(b)) => return Ok(compare(op, a.cmp(b))),
+            _ => return Err(at(format!("Operator '{}' expects numbers, got {} and {}", op.symbol(), lhs, rhs))),
+        };
+        let result = match op {
+            BinOp::Add => a.checked_add(b),
+            BinOp::Sub => a.checked_sub(b),
+            BinOp::Mul => a.checked_mul(b),
+            BinOp::Div => {
+                if b == 0 {
+                    return Err(self.raise_builtin("Div", "division by zero".to_string(), span));
+                }
+                // SML's `div` rounds towards negative infinity
+                a.checked_div(b).map(|q| if a % b != 0 && ((a < 0) != (b < 0)) { q - 1 } else { q })
+            },
+            BinOp::Lt => return Ok(Value::Bool(a < b)),
+            BinOp::Lte => return Ok(Value::Bool(a <= b)),
+            BinOp::Gt => return Ok(Value::Bool(a > b)),
+            BinOp::Gte => return Ok(Value::Bool(a >= b)),
+            BinOp::Eq | BinOp::Neq | BinOp::Cons => unreachable!("handled above"),
+        };
+        match result {
+            Some(n) => Ok(Value::Int(n)),
+            None => Err(self.raise_builtin("Overflow", "integer overflow".to_string(), span)),
+        }
+    }
+
+    fn visit_neg(&mut self, expr: &AstNode, span: &Span) -> Result<Value, Diagnostic> {
+        match self.visit_node(expr)? {
+            Value::Int(n) => match n.checked_neg() {
+                Some(n) => Ok(Value::Int(n)),
+                None => Err(self.raise_builtin("Overflow", "integer overflow".to_string(), span)),
+            },
+            Value::Real(x) => Ok(Value::Real(-x)),
+            other => Err(runtime_error(format!("Can not negate {}", other), span)),
+        }
+    }
+
+    fn visit_ref(&mut self, expr: &AstNode, _span: &Span) -> Result<Value, Diagnostic> {
+        let value = self.visit_node(expr)?;
+        Ok(Value::Ref(Rc::new(RefCell::new(value))))
+    }
+
+    fn visit_deref(&mut self, expr: &AstNode, span: &Span) -> Result<Value, Diagnostic> {
+        match self.visit_node(expr)? {
+            Value::Ref(cell) => Ok(cell.borrow().clone()),
+            other => Err(runtime_error(format!("Can not dereference non-ref value {}", other), span)),
+        }
+    }
+
+    fn visit_assign(&mut self, target: &AstNode, value: &AstNode, span: &Span) -> Result<Value, Diagnostic> {
+        let target = self.visit_node(target)?;
+        let value = self.visit_node(value)?;
+        match target {
+            Value::Ref(cell) => {
+                *cell.borrow_mut() = value;
+                Ok(Value::Unit)
+            },
+            other => Err(runtime_error(format!("Can not assign to non-ref value {}", other), span)),
+        }
+    }
+
+    fn visit_andalso(&mut self, left: &AstNode, right: &AstNode, _span: &Span) -> Result<Value, Diagnostic> {
+        if self.eval_bool(left)? {
+            self.visit_node(right)
+        } else {
+            Ok(Value::Bool(false))
+        }
+    }
+
+    fn visit_orelse(&mut self, left: &AstNode, right: &AstNode, _span: &Span) -> Result<Value, Diagnostic> {
+        if self.eval_bool(left)? {
+            Ok(Value::Bool(true))
+        } else {
+            self.visit_node(right)
+        }
+    }
+
+    fn visit_seq(&mut self, exprs: &[Box<AstNode>], _span: &Span) -> Result<Value, Diagnostic> {
+        let mut value = Value::Unit;
+        for expr in exprs {
+            value = self.visit_node(expr)?;
+        }
+        Ok(value)
+    }
+
+    fn visit_raise(&mut self, expr: &AstNode, span: &Span) -> Result<Value, Diagnostic> {
+        let exn = self.visit_node(expr)?;
+        Err(self.raise(exn, "raised here", span))
+    }
+
+    fn visit_handle(&mut self, expr: &AstNode, arms: &Vec<(AstPattern, Box<AstNode>)>, _span: &Span)
+        -> Result<Value, Diagnostic>
+    {
+        let env = self.env.clone();
+        let err = match self.visit_node(expr) {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+        // Errors that are not exceptions can not be handled
+        let Some(exn) = self.raised.take() else {
+            return Err(err);
+        };
+        self.env = env.clone();
+        match self.eval_arms(arms, &env, &exn)? {
+            Some(value) => Ok(value),
+            None => {
+                // No arm matches, so the exception carries on unwinding
+                self.raised = Some(exn);
+                Err(err)
+            },
+        }
+    }
+
+    fn visit_app(&mut self, func: &AstNode, arg: &AstNode, span: &Span) -> Result<Value, Diagnostic> {
+        let func = self.visit_node(func)?;
+        let arg = self.visit_node(arg)?;
+        self.apply(func, arg, span)
+    }
+
+    fn visit_id(&mut self, name: &str, span: &Span) -> Result<Value, Diagnostic> {
+        self.env
+            .lookup(name)
+            .ok_or_else(|| runtime_error(format!("Unbound identifier '{}'", name), span))
+    }
+
+    fn visit_var(&mut self, name: &str, span: &Span) -> Result<Value, Diagnostic> {
+        self.visit_id(name, span)
+    }
+
+    fn visit_tuple(&mut self, elements: &Vec<Box<AstNode>>, _span: &Span) -> Result<Value, Diagnostic> {
+        if elements.is_empty() {
+            return Ok(Value::Unit);
+        }
+        let mut values = Vec::new();
+        for element in elements {
+            values.push(self.visit_node(element)?);
+        }
+        Ok(Value::Tuple(values))
+    }
+
+    fn visit_record(&mut self, fields: &[(String, Box<AstNode>)], _span: &Span) -> Result<Value, Diagnostic> {
+        let mut values = Vec::new();
+        for (label, expr) in fields {
+            values.push((label.clone(), self.visit_node(expr)?));
+        }
+        Ok(Value::record(values))
+    }
+
+    fn visit_selector(&mut self, label: &str, _span: &Span) -> Result<Value, Diagnostic> {
+        Ok(Value::Selector(label.to_string()))
+    }
+
+    fn visit_list(&mut self, elements: &Vec<Box<AstNode>>, _span: &Span) -> Result<Value, Diagnostic> {
+        let mut values = Vec::new();
+        for element in elements {
+            values.push(self.visit_node(element)?);
+        }
+        Ok(Value::List(values))
+    }
+
+    fn visit_literal(&mut self, lit: &LiteralValue, span: &Span) -> Result<Value, Diagnostic> {
+        literal_value(lit).map_err(|e| runtime_error(e, span))
+    }
+
+    fn visit_error(&mut self, span: &Span) -> Result<Value, Diagnostic> {
+        Err(runtime_error("Can not evaluate code that failed to parse".to_string(), span))
+    }
+}
+
+fn real_bin_op(op: &BinOp, a: f64, b: f64) -> Value {
+    match op {
+        BinOp::Add => Value::Real(a + b),
+        BinOp::Sub => Value::Real(a - b),
+        BinOp::Mul => Value::Real(a * b),
+        BinOp::Div => Value::Real(a / b),
+        BinOp::Lt => Value::Bool(a < b),
+        BinOp::Lte => Value::Bool(a <= b),
+        BinOp::Gt => Value::Bool(a > b),
+        BinOp::Gte => Value::Bool(a >= b),
+        BinOp::Eq | BinOp::Neq | BinOp::Cons => unreachable!("handled by the caller"),
+    }
+}
+
+/// The comparison `op` of two strings or chars, which are ordered by the
+/// codes of their characters.
+fn compare(op: &BinOp, ordering: Ordering) -> Value {
+    match op {
+        BinOp::Lt => Value::Bool(ordering.is_lt()),
+        BinOp::Lte => Value::Bool(ordering.is_le()),
+        BinOp::Gt => Value::Bool(ordering.is_gt()),
+        BinOp::Gte => Value::Bool(ordering.is_ge()),
+        _ => unreachable!("strings and chars only support comparisons"),
+    }
+}
+
+fn literal_value(lit: &LiteralValue) -> Result<Value, String> {
+    match lit {
+        LiteralValue::Integer(s) => s
+            .replace('~', "-")
+            .parse()
+            .map(Value::Int)
+            .map_err(|_| format!("Integer literal {} is out of range", s)),
+        LiteralValue::Real(s) => s
+            .replace('~', "-")
+            .parse()
+            .map(Value::Real)
+            .map_err(|_| format!("Invalid real literal {}", s)),
+        LiteralValue::Boolean(s) => Ok(Value::Bool(s == "true")),
+        LiteralValue::String(s) => Ok(Value::String(s.clone())),
+        LiteralValue::Char(s) => s.chars().next()
+            .map(Value::Char)
+            .ok_or_else(|| "Empty character literal".to_string()),
+    }
+}
+
+/// Roughly the current stack pointer: the address of a local variable.
+#[inline(never)]
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+fn runtime_error(msg: String, span: &Span) -> Diagnostic {
+    Diagnostic::error(msg).with_primary(*span, "")
+}
+
+/// Match `value` against `pat`, pushing the variables it binds onto `bound`.
+/// Returns `Ok(false)` if the value does not fit the pattern. Exception
+/// constructors in `pat` are looked up in `env`.
+fn match_pattern(pat: &AstPattern, value: &Value, env: &Env, bound: &mut Vec<(String, Value)>)
+    -> Result<bool, String>
+{
+    match (pat, value) {
+        (AstPattern::Wildcard(_), _) => Ok(true),
+        (AstPattern::Id(name, _), _) | (AstPattern::Var(name, _), _) => {
+            bound.push((name.clone(), value.clone()));
+            Ok(true)
+        },
+        (AstPattern::Pair(first, second, _), Value::Tuple(values)) if values.len() == 2 => {
+            Ok(match_pattern(first, &values[0], env, bound)?
+                && match_pattern(second, &values[1], env, bound)?)
+        },
+        (AstPattern::Pair(..), _) => Ok(false),
+        (AstPattern::Tuple(pats, _), Value::Unit) => Ok(pats.is_empty()),
+        (AstPattern::Tuple(pats, _), Value::Tuple(values)) if pats.len() == values.len() => {
+            for (pat, value) in pats.iter().zip(values) {
+                if !match_pattern(pat, value, env, bound)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        },
+        (AstPattern::Constructor(name, arg, _), Value::Data(tag, value)) => {
+            if name != tag {
+                return Ok(false);
+            }
+            match (arg, value) {
+                (Some(arg), Some(value)) => match_pattern(arg, value, env, bound),
+                (None, None) => Ok(true),
+                _ => Ok(false),
+            }
+        },
+        (AstPattern::Constructor(name, arg, _), Value::Exn(exn, value)) => {
+            let declared = match env.lookup(name) {
+                Some(Value::Exn(declared, _) | Value::ExnConstructor(declared)) => declared,
+                _ => return Ok(false),
+            };
+            if !Rc::ptr_eq(&declared, exn) {
+                return Ok(false);
+            }
+            match (arg, value) {
+                (Some(arg), Some(value)) => match_pattern(arg, value, env, bound),
+                (None, None) => Ok(true),
+                _ => Ok(false),
+            }
+        },
+        (AstPattern::Tuple(..), _) | (AstPattern::Constructor(..), _) => Ok(false),
+        (AstPattern::Cons(head, tail, _), Value::List(values)) if !values.is_empty() => {
+            Ok(match_pattern(head, &values[0], env, bound)?
+                && match_pattern(tail, &Value::List(values[1..].to_vec()), env, bound)?)
+        },
+        (AstPattern::Nil(_), Value::List(values)) => Ok(values.is_empty()),
+        (AstPattern::List(pats, _), Value::List(values)) if pats.len() == values.len() => {
+            for (pat, value) in pats.iter().zip(values) {
+                if !match_pattern(pat, value, env, bound)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        },
+        (AstPattern::Cons(..), _) | (AstPattern::Nil(_), _) | (AstPattern::List(..), _) => Ok(false),
+        (AstPattern::Ref(inner, _), Value::Ref(cell)) => match_pattern(inner, &cell.borrow(), env, bound),
+        (AstPattern::Ref(..), _) => Ok(false),
+        // The type checker makes sure the record has the fields
+        (AstPattern::Record(fields, _, _), _) => {
+            for (label, pat) in fields {
+                match value.field(label) {
+                    Some(field) if match_pattern(pat, field, env, bound)? => {},
+                    _ => return Ok(false),
+                }
+            }
+            Ok(true)
+        },
+        (AstPattern::Literal(lit, _), _) => Ok(value.equals(&literal_value(lit)?)),
+    }
+}
@@ -0,0 +1,1219 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use crate::ast::{AstNode, AstPattern, ConstructorDecl, FunBinding, Type, TypeAnnotation, BinOp, LiteralValue};
+use crate::ast_visitor::Visitable;
+use crate::span::Span;
+use crate::diagnostic::Diagnostic;
+
+/// A polymorphic type `forall vars. ty`, created when a `val` or `fun`
+/// binding is generalised.
+#[derive(Debug, Clone)]
+pub struct Scheme {
+    pub vars: Vec<String>,
+    pub ty: Type,
+}
+
+impl Scheme {
+    pub fn mono(ty: Type) -> Self {
+        Scheme { vars: Vec::new(), ty }
+    }
+}
+
+impl fmt::Display for Scheme {
+    /// Variables that are not quantified stand for a single type that is not
+    /// known yet, so they are shown as '_a rather than 'a.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mapping: HashMap<String, Type> = ordered_vars(&self.ty)
+            .into_iter()
+            .enumerate()
+            .map(|(n, var)| {
+                let name = if self.vars.contains(&var) { var_name(n) } else { format!("_{}", var_name(n)) };
+                (var.clone(), Type::Var(display_var(&var, name)))
+            })
+            .collect();
+        write!(f, "{}", rename(&self.ty, &mapping))
+    }
+}
+
+/// A function binding borrowed from a `FunDecl` or a `FunGroup`: its name,
+/// clauses, type annotation and span.
+type FunRef<'a> = (&'a str, &'a Vec<(AstPattern, Box<AstNode>)>, &'a Option<TypeAnnotation>, &'a Span);
+
+/// A type that must be a record with at least the given fields, and where
+/// that was required.
+type FlexRecord = (Type, Vec<(String, Type)>, Span);
+
+/// The types a built-in operator is overloaded on.
+#[derive(Debug, Clone, Copy)]
+enum Overload {
+    /// int or real, for arithmetic and `~`.
+    Numeric,
+    /// int, real, string or char, for `<`, `<=`, `>` and `>=`.
+    Ordered,
+}
+
+impl Overload {
+    fn admits(self, ty: &Type) -> bool {
+        match ty {
+            Type::Int | Type::Real => true,
+            Type::String | Type::Char => matches!(self, Overload::Ordered),
+            _ => false,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Overload::Numeric => "int or real",
+            Overload::Ordered => "int, real, string or char",
+        }
+    }
+}
+
+/// The bindings of one lexical scope.
+#[derive(Debug, Default)]
+struct Scope {
+    values: HashMap<String, Scheme>,
+    /// which of `values` are datatype or exception constructors, and whether
+    /// each takes an argument.
+    constructors: HashMap<String, bool>,
+    /// type constructors declared here.
+    types: HashMap<String, TypeDecl>,
+}
+
+/// What a type constructor's name stands for.
+#[derive(Debug, Clone, Copy)]
+struct TypeDecl {
+    /// the number of arguments it takes.
+    arity: usize,
+    /// whether its values can be compared with `=` if its arguments can.
+    equality: bool,
+}
+
+impl Scope {
+    /// Add the bindings of `inner`, shadowing any made here before.
+    fn extend(&mut self, inner: Scope) {
+        for name in inner.values.keys() {
+            self.constructors.remove(name);
+        }
+        self.values.extend(inner.values);
+        self.constructors.extend(inner.constructors);
+        self.types.extend(inner.types);
+    }
+}
+
+/// Hindley-Milner type inference over the AST.
+///
+/// Each visit returns the type of the visited node. The substitution is kept
+/// on the visitor and extended in place by `unify`, Algorithm J style, so the
+/// types returned from the visit methods may still mention variables that are
+/// solved later on; call `apply` to get the current view of a type.
+pub struct InferVisitor {
+    /// stack of lexical scopes, innermost last.
+    env: Vec<Scope>,
+    subst: HashMap<String, Type>,
+    next_var: usize,
+    /// generalised types of the top-level bindings made since
+    /// `take_bindings` was last called, in declaration order.
+    bindings: Vec<(String, Scheme)>,
+    /// operand types of overloaded operators that are not yet known, with
+    /// the types allowed, the operator and where it was used.
+    overloads: Vec<(Type, Overload, &'static str, Span)>,
+    /// types that `#label` or a `{..., ...}` pattern needs to be records
+    /// with the given fields, but that are not known yet.
+    flex_records: Vec<FlexRecord>,
+    /// number of scopes in `env` at top level. It is more than one in the
+    /// public part of a top-level `local`, whose bindings are top-level too.
+    top_level_depth: usize,
+}
+
+impl Default for InferVisitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InferVisitor {
+    pub fn new() -> Self {
+        let builtin_types = [("list", 1, true), ("ref", 1, true), ("exn", 0, false)];
+        let mut visitor = InferVisitor {
+            env: vec![Scope {
+                types: builtin_types
+                    .iter()
+                    .map(|&(name, arity, equality)| (name.to_string(), TypeDecl { arity, equality }))
+                    .collect(),
+                ..Scope::default()
+            }],
+            subst: HashMap::new(),
+            next_var: 0,
+            bindings: Vec::new(),
+            overloads: Vec::new(),
+            flex_records: Vec::new(),
+            top_level_depth: 1,
+        };
+        visitor.visit(&AstNode::option_datatype()).expect("the option datatype is well formed");
+        visitor.visit(&AstNode::builtin_exceptions()).expect("the built-in exceptions are well formed");
+        visitor.visit(&AstNode::builtin_functions()).expect("the built-in functions are well formed");
+        // Only the program's own bindings are shown
+        visitor.bindings.clear();
+        visitor
+    }
+
+    /// The types of the top-level bindings made since the last call, as far
+    /// as they are known at the end of the declaration that made them.
+    pub fn take_bindings(&mut self) -> Vec<(String, Scheme)> {
+        std::mem::take(&mut self.bindings)
+            .into_iter()
+            .map(|(name, scheme)| {
+                let ty = self.apply(&scheme.ty);
+                (name, Scheme { vars: scheme.vars, ty })
+            })
+            .collect()
+    }
+
+    fn fresh(&mut self) -> Type {
+        self.fresh_var(false)
+    }
+
+    /// A fresh type variable, which stands only for types that admit
+    /// equality if `equality` is set.
+    fn fresh_var(&mut self, equality: bool) -> Type {
+        // '?' can not appear in a source identifier, so fresh variables never
+        // collide with the ones written in annotations.
+        let prefix = if equality { "'" } else { "" };
+        let var = Type::Var(format!("{}?{}", prefix, self.next_var));
+        self.next_var += 1;
+        var
+    }
+
+    /// Resolve all solved type variables in `ty` under the current substitution.
+    pub fn apply(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Var(name) => match self.subst.get(name) {
+                Some(t) => self.apply(t),
+                None => ty.clone(),
+            },
+            Type::Arrow(param, ret) => {
+                Type::Arrow(Box::new(self.apply(param)), Box::new(self.apply(ret)))
+            },
+            Type::Product(types) => {
+                Type::Product(types.iter().map(|t| self.apply(t)).collect())
+            },
+            Type::Con(name, args) => {
+                Type::Con(name.clone(), args.iter().map(|t| self.apply(t)).collect())
+            },
+            Type::Record(fields) => {
+                Type::Record(fields.iter().map(|(label, t)| (label.clone(), self.apply(t))).collect())
+            },
+            _ => ty.clone(),
+        }
+    }
+
+    /// Unify `found`, the type of the code at `span`, with the type it is
+    /// `expected` to have.
+    fn unify(&mut self, expected: &Type, found: &Type, span: &Span) -> Result<(), Diagnostic> {
+        let t1 = self.apply(expected);
+        let t2 = self.apply(found);
+        self.unify_types(&t1, &t2).map_err(|err| match err {
+            Some(msg) => Diagnostic::error("Occurs check failed").with_primary(*span, msg),
+            None => {
+                let shown = pretty(&[&t1, &t2]);
+                Diagnostic::error("Type mismatch")
+                    .with_primary(*span, format!("expected {}, found {}", shown[0], shown[1]))
+            },
+        })
+    }
+
+    /// Unify two types that have already had the substitution applied. A plain
+    /// mismatch is reported as `None` by the caller, which knows the full types
+    /// involved.
+    fn unify_types(&mut self, t1: &Type, t2: &Type) -> Result<(), Option<String>> {
+        match (t1, t2) {
+            (Type::Var(a), Type::Var(b)) if a == b => Ok(()),
+            (Type::Var(a), t) | (t, Type::Var(a)) => self.bind_var(a, t),
+            (Type::Arrow(p1, r1), Type::Arrow(p2, r2)) => {
+                self.unify_types(p1, p2)?;
+                let r1 = self.apply(r1);
+                let r2 = self.apply(r2);
+                self.unify_types(&r1, &r2)
+            },
+            (Type::Product(ts1), Type::Product(ts2)) if ts1.len() == ts2.len() => {
+                self.unify_all(ts1, ts2)
+            },
+            (Type::Con(n1, args1), Type::Con(n2, args2))
+                if n1 == n2 && args1.len() == args2.len() => {
+                self.unify_all(args1, args2)
+            },
+            (Type::Record(fs1), Type::Record(fs2))
+                if fs1.len() == fs2.len() && fs1.iter().zip(fs2).all(|((l1, _), (l2, _))| l1 == l2) => {
+                let ts1: Vec<Type> = fs1.iter().map(|(_, t)| t.clone()).collect();
+                let ts2: Vec<Type> = fs2.iter().map(|(_, t)| t.clone()).collect();
+                self.unify_all(&ts1, &ts2)
+            },
+            (a, b) if a == b => Ok(()),
+            _ => Err(None),
+        }
+    }
+
+    fn unify_all(&mut self, ts1: &[Type], ts2: &[Type]) -> Result<(), Option<String>> {
+        for (a, b) in ts1.iter().zip(ts2) {
+            let a = self.apply(a);
+            let b = self.apply(b);
+            self.unify_types(&a, &b)?;
+        }
+        Ok(())
+    }
+
+    fn bind_var(&mut self, var: &str, ty: &Type) -> Result<(), Option<String>> {
+        if free_vars(ty).contains(var) {
+            let shown = pretty(&[&Type::Var(var.to_string()), ty]);
+            return Err(Some(format!("cannot construct the infinite type {} = {}",
+                                    shown[0], shown[1])));
+        }
+        if is_equality_var(var) {
+            if !self.admits_equality(ty) {
+                return Err(None);
+            }
+            self.make_equality(ty);
+        }
+        self.subst.insert(var.to_string(), ty.clone());
+        Ok(())
+    }
+
+    /// Require `ty`, the type of the operands of `=` or `<>` at `span`, to
+    /// admit equality.
+    fn equality(&mut self, ty: &Type, op: &str, span: &Span) -> Result<(), Diagnostic> {
+        let ty = self.apply(ty);
+        if !self.admits_equality(&ty) {
+            return Err(Diagnostic::error(format!("Operator '{}' expects operands that admit equality", op))
+                .with_primary(*span, format!("found {}", pretty(&[&ty])[0]))
+                .with_note("functions, reals and exceptions can not be compared with '=' or '<>'"));
+        }
+        self.make_equality(&ty);
+        Ok(())
+    }
+
+    /// Whether values of `ty` can be compared for equality, supposing that
+    /// its type variables will only stand for types that can.
+    fn admits_equality(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Real | Type::Arrow(..) => false,
+            // Cells are compared by identity, whatever they hold
+            Type::Con(name, _) if name == "ref" => true,
+            Type::Con(name, args) => {
+                self.type_decl(name).is_some_and(|decl| decl.equality)
+                    && args.iter().all(|arg| self.admits_equality(arg))
+            },
+            Type::Product(types) => types.iter().all(|t| self.admits_equality(t)),
+            Type::Record(fields) => fields.iter().all(|(_, t)| self.admits_equality(t)),
+            _ => true,
+        }
+    }
+
+    /// Turn the type variables in `ty` into equality type variables.
+    fn make_equality(&mut self, ty: &Type) {
+        for var in free_vars(&self.apply(ty)) {
+            if !is_equality_var(&var) {
+                let eq = self.fresh_var(true);
+                self.subst.insert(var, eq);
+            }
+        }
+    }
+
+    fn env_free_vars(&self) -> HashSet<String> {
+        self.env
+            .iter()
+            .flat_map(|scope| scope.values.values())
+            .flat_map(|scheme| {
+                let bound: HashSet<&String> = scheme.vars.iter().collect();
+                free_vars(&self.apply(&scheme.ty))
+                    .into_iter()
+                    .filter(move |v| !bound.contains(v))
+            })
+            .collect()
+    }
+
+    fn generalize(&self, ty: &Type) -> Scheme {
+        let ty = self.apply(ty);
+        let env_vars = self.env_free_vars();
+        // Overloaded types and flexible records are resolved, not quantified
+        let overloaded: HashSet<String> = self.overloads
+            .iter()
+            .flat_map(|(t, ..)| free_vars(&self.apply(t)))
+            .chain(self.flex_records.iter().flat_map(|(record, fields, _)| {
+                std::iter::once(record)
+                    .chain(fields.iter().map(|(_, t)| t))
+                    .flat_map(|t| free_vars(&self.apply(t)))
+            }))
+            .collect();
+        let mut vars = Vec::new();
+        for var in ordered_vars(&ty) {
+            if !env_vars.contains(&var) && !overloaded.contains(&var) {
+                vars.push(var);
+            }
+        }
+        Scheme { vars, ty }
+    }
+
+    /// Require `ty` to be one of the types `op` is overloaded on. If it is
+    /// not known yet the check is deferred to `resolve_overloads`.
+    fn overloaded(&mut self, ty: &Type, overload: Overload, op: &'static str, span: &Span)
+        -> Result<(), Diagnostic>
+    {
+        match self.apply(ty) {
+            Type::Var(_) => {
+                self.overloads.push((ty.clone(), overload, op, *span));
+                Ok(())
+            },
+            known if overload.admits(&known) => Ok(()),
+            other => Err(Diagnostic::error(format!("Operator '{}' expects {} operands", op, overload.describe()))
+                .with_primary(*span, format!("found {}", pretty(&[&other])[0]))),
+        }
+    }
+
+    /// Settle the deferred overloads at the end of a top-level declaration.
+    /// As in SML, a type that is still unknown defaults to int.
+    fn resolve_overloads(&mut self) -> Result<(), Diagnostic> {
+        for (ty, overload, op, span) in std::mem::take(&mut self.overloads) {
+            if let Type::Var(var) = self.apply(&ty) {
+                self.subst.insert(var, Type::Int);
+            }
+            self.overloaded(&ty, overload, op, &span)?;
+        }
+        Ok(())
+    }
+
+    /// Require `record` to be a record or tuple type with `fields`. If it is
+    /// not known yet the check is deferred to `resolve_records`, as SML
+    /// has no types for records with unknown fields.
+    fn select_fields(&mut self, record: &Type, fields: Vec<(String, Type)>, span: &Span)
+        -> Result<(), Diagnostic>
+    {
+        let known = match self.apply(record) {
+            Type::Var(_) => {
+                self.flex_records.push((record.clone(), fields, *span));
+                return Ok(());
+            },
+            Type::Record(known) => known,
+            Type::Product(types) => types
+                .into_iter()
+                .enumerate()
+                .map(|(i, t)| ((i + 1).to_string(), t))
+                .collect(),
+            Type::Unit => Vec::new(),
+            other => {
+                return Err(Diagnostic::error("Type mismatch")
+                    .with_primary(*span, format!("expected a record, found {}", pretty(&[&other])[0])));
+            },
+        };
+        for (label, ty) in fields {
+            match known.iter().find(|(l, _)| *l == label) {
+                Some((_, field_ty)) => self.unify(field_ty, &ty, span)?,
+                None => {
+                    let shown = pretty(&[&self.apply(record)]);
+                    return Err(Diagnostic::error(format!("Record type {} has no field '{}'", shown[0], label))
+                        .with_primary(*span, format!("field '{}' is used here", label)));
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the deferred record fields whose record type is known by now.
+    /// At the end of a top-level declaration all of them must be known.
+    fn resolve_records(&mut self, top_level: bool) -> Result<(), Diagnostic> {
+        // Checking one record may tell the type of another, so repeat
+        // until nothing changes
+        loop {
+            let pending = std::mem::take(&mut self.flex_records);
+            let count = pending.len();
+            for (record, fields, span) in pending {
+                self.select_fields(&record, fields, &span)?;
+            }
+            if self.flex_records.len() == count {
+                break;
+            }
+        }
+        match self.flex_records.first() {
+            Some((_, _, span)) if top_level => Err(Diagnostic::error("Unresolved flexible record")
+                .with_primary(*span, "the other fields of this record are not known")
+                .with_note("add a type annotation that gives all of its fields")),
+            _ => Ok(()),
+        }
+    }
+
+    fn instantiate(&mut self, scheme: &Scheme) -> Type {
+        let mapping: HashMap<String, Type> = scheme.vars
+            .iter()
+            .map(|v| (v.clone(), self.fresh_var(is_equality_var(v))))
+            .collect();
+        rename(&scheme.ty, &mapping)
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Scheme> {
+        self.env.iter().rev().find_map(|scope| scope.values.get(name))
+    }
+
+    /// Whether `name` is bound to a constructor, and if so whether it takes
+    /// an argument.
+    fn constructor(&self, name: &str) -> Option<bool> {
+        self.env
+            .iter()
+            .rev()
+            .find(|scope| scope.values.contains_key(name))
+            .and_then(|scope| scope.constructors.get(name).copied())
+    }
+
+    fn bind(&mut self, name: &str, scheme: Scheme) {
+        let scope = self.env.last_mut().expect("type environment has no scope");
+        scope.values.insert(name.to_string(), scheme);
+        scope.constructors.remove(name);
+    }
+
+    fn bind_constructor(&mut self, name: &str, scheme: Scheme, takes_arg: bool) {
+        self.bind(name, scheme);
+        let scope = self.env.last_mut().expect("type environment has no scope");
+        scope.constructors.insert(name.to_string(), takes_arg);
+    }
+
+    fn declare_type(&mut self, name: &str, decl: TypeDecl) {
+        let scope = self.env.last_mut().expect("type environment has no scope");
+        scope.types.insert(name.to_string(), decl);
+    }
+
+    fn type_decl(&self, name: &str) -> Option<TypeDecl> {
+        self.env.iter().rev().find_map(|scope| scope.types.get(name).copied())
+    }
+
+    /// Check that every type constructor in `ty`, written at `span`, is in
+    /// scope and given as many arguments as it takes.
+    fn check_type(&self, ty: &Type, span: &Span) -> Result<(), Diagnostic> {
+        match ty {
+            Type::Con(name, args) => {
+                match self.type_decl(name).map(|decl| decl.arity) {
+                    None => {
+                        return Err(Diagnostic::error(format!("Unbound type constructor '{}'", name))
+                            .with_primary(*span, "not found in this scope"));
+                    },
+                    Some(arity) if arity != args.len() => {
+                        let msg = format!("Type constructor '{}' takes {} argument(s), but is given {}",
+                                          name, arity, args.len());
+                        return Err(Diagnostic::error(msg).with_primary(*span, format!("in {}", ty)));
+                    },
+                    Some(_) => {},
+                }
+                args.iter().try_for_each(|arg| self.check_type(arg, span))
+            },
+            Type::Arrow(param, ret) => {
+                self.check_type(param, span)?;
+                self.check_type(ret, span)
+            },
+            Type::Product(types) => types.iter().try_for_each(|t| self.check_type(t, span)),
+            Type::Record(fields) => fields.iter().try_for_each(|(_, t)| self.check_type(t, span)),
+            _ => Ok(()),
+        }
+    }
+
+    fn is_top_level(&self) -> bool {
+        self.env.len() == self.top_level_depth
+    }
+
+    /// Check the types named in an annotation and replace its type variables
+    /// with fresh ones. The mapping is returned so `check_annotation` can
+    /// verify afterwards that the annotation was not more general than the
+    /// inferred type.
+    fn annotation(&mut self, typ: &TypeAnnotation) -> Result<(Type, HashMap<String, Type>), Diagnostic> {
+        self.check_type(&typ.ty, &typ.span)?;
+        let mapping: HashMap<String, Type> = ordered_vars(&typ.ty)
+            .into_iter()
+            .map(|v| {
+                let fresh = self.fresh_var(is_equality_var(&v));
+                (v, fresh)
+            })
+            .collect();
+        Ok((rename(&typ.ty, &mapping), mapping))
+    }
+
+    fn check_annotation(&self, typ: &TypeAnnotation, mapping: &HashMap<String, Type>, inferred: &Type)
+        -> Result<(), Diagnostic>
+    {
+        let mut seen = HashSet::new();
+        // The '%n variables stand for argument types that a clausal fun's
+        // result type annotation leaves open, so they may be anything
+        let written = mapping.iter().filter(|(name, _)| !name.starts_with('%'));
+        for (name, var) in written {
+            match self.apply(var) {
+                // A plain 'a can not stand for a type that must admit equality
+                Type::Var(v) if seen.insert(v.clone()) && (is_equality_var(name) || !is_equality_var(&v)) => {},
+                _ => {
+                    let shown = pretty(&[&typ.ty, &self.apply(inferred)]);
+                    return Err(Diagnostic::error("Type annotation is more general than the inferred type")
+                        .with_primary(typ.span, format!("annotation is {}", shown[0]))
+                        .with_note(format!("the inferred type is {}", shown[1])));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Infer functions that may call each other. Each one is monomorphic in
+    /// the bodies of the group, and they are generalised together at the end.
+    fn infer_fun_group(&mut self, funs: &[FunRef]) -> Result<Vec<Type>, Diagnostic> {
+        let mut fun_tys = Vec::new();
+        let mut annotations = Vec::new();
+        for (_, _, typ, _) in funs {
+            let fun_ty = self.fresh();
+            if let Some(t) = typ {
+                let (ann, mapping) = self.annotation(t)?;
+                self.unify(&ann, &fun_ty, &t.span)?;
+                annotations.push((t, mapping, fun_ty.clone()));
+            }
+            fun_tys.push(fun_ty);
+        }
+
+        self.env.push(Scope::default());
+        for ((name, ..), fun_ty) in funs.iter().zip(&fun_tys) {
+            self.bind(name, Scheme::mono(fun_ty.clone()));
+        }
+        let inferred = funs.iter().zip(&fun_tys).try_for_each(|((_, clauses, _, span), fun_ty)| {
+            let clauses_ty = self.infer_clauses(clauses)?;
+            self.unify(fun_ty, &clauses_ty, span)
+        });
+        self.env.pop();
+        inferred?;
+
+        for (t, mapping, fun_ty) in annotations {
+            self.check_annotation(t, &mapping, &fun_ty)?;
+        }
+
+        self.resolve_records(self.is_top_level())?;
+        if self.is_top_level() {
+            self.resolve_overloads()?;
+        }
+
+        // Generalise all of them before binding any, so the group's types
+        // do not count as free in the environment
+        let schemes: Vec<Scheme> = fun_tys.iter().map(|ty| self.generalize(ty)).collect();
+        for ((name, ..), scheme) in funs.iter().zip(schemes) {
+            if self.is_top_level() {
+                self.bindings.push((name.to_string(), scheme.clone()));
+            }
+            self.bind(name, scheme);
+        }
+        Ok(fun_tys.iter().map(|ty| self.apply(ty)).collect())
+    }
+
+    /// Whether evaluating `exp` can not allocate a cell or raise, making it
+    /// safe to generalise: literals, variables, functions, and constructors,
+    /// tuples and records made of such values.
+    fn is_nonexpansive(&self, exp: &AstNode) -> bool {
+        match exp {
+            AstNode::Literal(..) | AstNode::Id(..) | AstNode::Var(..) | AstNode::Fn { .. }
+            | AstNode::Selector { .. } => true,
+            AstNode::Tuple(elements, _) | AstNode::List(elements, _) => {
+                elements.iter().all(|element| self.is_nonexpansive(element))
+            },
+            AstNode::Record { fields, .. } => fields.iter().all(|(_, field)| self.is_nonexpansive(field)),
+            AstNode::App { func, arg, .. } => {
+                matches!(&**func, AstNode::Id(name, _) if self.constructor(name).is_some())
+                    && self.is_nonexpansive(arg)
+            },
+            _ => false,
+        }
+    }
+
+    /// Infer the type of a list of `pat => exp` clauses as used by `fn` and
+    /// `fun`. All patterns share one type, as do all bodies.
+    fn infer_clauses(&mut self, clauses: &Vec<(AstPattern, Box<AstNode>)>) -> Result<Type, Diagnostic> {
+        let arg = self.fresh();
+        let res = self.infer_arms(&arg, clauses)?;
+        Ok(Type::Arrow(Box::new(arg), Box::new(res)))
+    }
+
+    /// Check every pattern against `arg` and return the type shared by the
+    /// bodies.
+    fn infer_arms(&mut self, arg: &Type, arms: &Vec<(AstPattern, Box<AstNode>)>) -> Result<Type, Diagnostic> {
+        let res = self.fresh();
+        for (pat, body) in arms {
+            self.env.push(Scope::default());
+            let pat_ty = self.visit_pattern(pat)?;
+            self.unify(arg, &pat_ty, pat.span())?;
+            let body_ty = self.visit_node(body)?;
+            self.env.pop();
+            self.unify(&res, &body_ty, body.span())?;
+        }
+        Ok(res)
+    }
+
+    /// Both operands of `andalso` and `orelse` are bool.
+    fn infer_connective(&mut self, left: &AstNode, right: &AstNode) -> Result<Type, Diagnostic> {
+        let left_ty = self.visit_node(left)?;
+        self.unify(&Type::Bool, &left_ty, left.span())?;
+        let right_ty = self.visit_node(right)?;
+        self.unify(&Type::Bool, &right_ty, right.span())?;
+        Ok(Type::Bool)
+    }
+
+    fn bind_pattern_var(&mut self, name: &str, span: &Span) -> Result<Type, Diagnostic> {
+        let scope = self.env.last().expect("type environment has no scope");
+        if scope.values.contains_key(name) {
+            let msg = format!("Variable '{}' is bound more than once in the same pattern", name);
+            return Err(Diagnostic::error(msg).with_primary(*span, "rebound here"));
+        }
+        let ty = self.fresh();
+        self.bind(name, Scheme::mono(ty.clone()));
+        Ok(ty)
+    }
+}
+
+impl Visitable<Type> for InferVisitor {
+
+    fn visit_program(&mut self, stmts: &Vec<Box<AstNode>>, _span: &Span) -> Result<Type, Diagnostic> {
+        for stmt in stmts {
+            self.visit_node(stmt)?;
+        }
+        Ok(Type::Unit)
+    }
+
+    fn visit_val_decl(&mut self, pat: &AstPattern, typ: &Option<TypeAnnotation>, exp: &AstNode,
+                      _span: &Span) -> Result<Type, Diagnostic>
+    {
+        let exp_ty = self.visit_node(exp)?;
+        if let Some(t) = typ {
+            let (ann, mapping) = self.annotation(t)?;
+            self.unify(&ann, &exp_ty, exp.span())
+                .map_err(|d| d.with_secondary(t.span, "expected due to this annotation"))?;
+            self.check_annotation(t, &mapping, &exp_ty)?;
+        }
+
+        // Bind the pattern variables in a scratch scope, so they can be
+        // generalised against the environment without them.
+        self.env.push(Scope::default());
+        let pat_ty = self.visit_pattern(pat);
+        let mut scope = self.env.pop().expect("type environment has no scope");
+        self.unify(&pat_ty?, &exp_ty, exp.span())?;
+
+        self.resolve_records(self.is_top_level())?;
+        if self.is_top_level() {
+            self.resolve_overloads()?;
+        }
+
+        let mut names = Vec::new();
+        pattern_names(pat, &mut names);
+        // The value restriction: only syntactic values are generalised, so
+        // that e.g. `ref []` can not be used at two different types
+        let generalizable = self.is_nonexpansive(exp);
+        for name in names {
+            if let Some(mono) = scope.values.remove(&name) {
+                let scheme = if generalizable {
+                    self.generalize(&mono.ty)
+                } else {
+                    Scheme::mono(self.apply(&mono.ty))
+                };
+                if self.is_top_level() {
+                    self.bindings.push((name.clone(), scheme.clone()));
+                }
+                self.bind(&name, scheme);
+            }
+        }
+        Ok(self.apply(&exp_ty))
+    }
+
+    fn visit_datatype_decl(&mut self, name: &str, params: &[String], constructors: &[ConstructorDecl],
+                           _span: &Span) -> Result<Type, Diagnostic>
+    {
+        let result = Type::Con(name.to_string(), params.iter().cloned().map(Type::Var).collect());
+        // The datatype is in scope in its own constructors. It admits equality
+        // unless one of them has an argument that does not
+        let mut decl = TypeDecl { arity: params.len(), equality: true };
+        self.declare_type(name, decl);
+        decl.equality = constructors
+            .iter()
+            .filter_map(|ctor| ctor.arg.as_ref())
+            .all(|arg| self.admits_equality(arg));
+        self.declare_type(name, decl);
+        for ctor in constructors {
+            let ty = match &ctor.arg {
+                Some(arg) => {
+                    if let Some(var) = ordered_vars(arg).into_iter().find(|v| !params.contains(v)) {
+                        let msg = format!("Unbound type variable '{} in datatype '{}'", var, name);
+                        return Err(Diagnostic::error(msg)
+                            .with_primary(ctor.span, "not a parameter of the datatype"));
+                    }
+                    self.check_type(arg, &ctor.span)?;
+                    Type::Arrow(Box::new(arg.clone()), Box::new(result.clone()))
+                },
+                None => result.clone(),
+            };
+            // The parameters are quantified, so each use gets fresh variables
+            self.bind_constructor(&ctor.name, Scheme { vars: params.to_vec(), ty }, ctor.arg.is_some());
+        }
+        Ok(Type::Unit)
+    }
+
+    fn visit_exception_decl(&mut self, name: &str, arg: &Option<Type>, span: &Span) -> Result<Type, Diagnostic> {
+        let ty = match arg {
+            Some(arg) => {
+                if let Some(var) = ordered_vars(arg).into_iter().next() {
+                    let msg = format!("Type variable '{} in exception '{}' is not allowed", var, name);
+                    return Err(Diagnostic::error(msg).with_primary(*span, "exceptions must have a fixed type"));
+                }
+                self.check_type(arg, span)?;
+                Type::Arrow(Box::new(arg.clone()), Box::new(exn()))
+            },
+            None => exn(),
+        };
+        self.bind_constructor(name, Scheme::mono(ty), arg.is_some());
+        Ok(Type::Unit)
+    }
+
+    fn visit_fun_decl(
+        &mut self, name: &str,
+        clauses: &Vec<(AstPattern, Box<AstNode>)>,
+        typ: &Option<TypeAnnotation>,
+        span: &Span) -> Result<Type, Diagnostic>
+    {
+        let mut tys = self.infer_fun_group(&[(name, clauses, typ, span)])?;
+        Ok(tys.remove(0))
+    }
+
+    fn visit_fun_group(&mut self, funs: &[FunBinding], _span: &Span) -> Result<Type, Diagnostic> {
+        let funs: Vec<FunRef> = funs
+            .iter()
+            .map(|fun| (fun.name.as_str(), &fun.clauses, &fun.typ, &fun.span))
+            .collect();
+        self.infer_fun_group(&funs)?;
+        Ok(Type::Unit)
+    }
+
+    fn visit_if(&mut self, cond: &AstNode, then: &AstNode, else_: &AstNode, _span: &Span)
+        -> Result<Type, Diagnostic>
+    {
+        let cond_ty = self.visit_node(cond)?;
+        self.unify(&Type::Bool, &cond_ty, cond.span())?;
+        let then_ty = self.visit_node(then)?;
+        let else_ty = self.visit_node(else_)?;
+        self.unify(&then_ty, &else_ty, else_.span())
+            .map_err(|d| d.with_secondary(*then.span(), "then branch has the expected type"))?;
+        Ok(self.apply(&then_ty))
+    }
+
+    fn visit_let(&mut self, decls: &[Box<AstNode>], body: &AstNode, _span: &Span) -> Result<Type, Diagnostic> {
+        self.env.push(Scope::default());
+        let body_ty = decls
+            .iter()
+            .try_for_each(|decl| self.visit_node(decl).map(drop))
+            .and_then(|_| self.visit_node(body));
+        self.env.pop();
+        body_ty
+    }
+
+    fn visit_local(&mut self, private: &[Box<AstNode>], public: &[Box<AstNode>], _span: &Span)
+        -> Result<Type, Diagnostic>
+    {
+        // The public part goes in a scope of its own on top of the private
+        // one, so its bindings can be moved out once both are popped
+        let top_level = self.is_top_level();
+        self.env.push(Scope::default());
+        let result = private.iter().try_for_each(|decl| self.visit_node(decl).map(drop));
+        self.env.push(Scope::default());
+        let saved_depth = self.top_level_depth;
+        if top_level {
+            self.top_level_depth = self.env.len();
+        }
+        let result = result.and_then(|_| public.iter().try_for_each(|decl| self.visit_node(decl).map(drop)));
+        self.top_level_depth = saved_depth;
+        let exported = self.env.pop().unwrap_or_default();
+        self.env.pop();
+        result?;
+
+        self.env.last_mut().expect("type environment has no scope").extend(exported);
+        Ok(Type::Unit)
+    }
+
+    fn visit_fn(&mut self, clauses: &Vec<(AstPattern, Box<AstNode>)>, _span: &Span) -> Result<Type, Diagnostic> {
+        self.infer_clauses(clauses)
+    }
+
+    fn visit_case(&mut self, scrutinee: &AstNode, arms: &Vec<(AstPattern, Box<AstNode>)>, _span: &Span)
+        -> Result<Type, Diagnostic>
+    {
+        let ty = self.visit_node(scrutinee)?;
+        let res = self.infer_arms(&ty, arms)?;
+        Ok(self.apply(&res))
+    }
+
+    fn visit_bin_op(&mut self, left: &AstNode, op: &BinOp, right: &AstNode, span: &Span)
+        -> Result<Type, Diagnostic>
+    {
+        let left_ty = self.visit_node(left)?;
+        let right_ty = self.visit_node(right)?;
+        match op {
+            BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div => {
+                self.unify(&left_ty, &right_ty, right.span())?;
+                self.overloaded(&left_ty, Overload::Numeric, op.symbol(), span)?;
+                Ok(self.apply(&left_ty))
+            },
+            BinOp::Lt | BinOp::Lte | BinOp::Gt | BinOp::Gte => {
+                self.unify(&left_ty, &right_ty, right.span())?;
+                self.overloaded(&left_ty, Overload::Ordered, op.symbol(), span)?;
+                Ok(Type::Bool)
+            },
+            BinOp::Eq | BinOp::Neq => {
+                self.unify(&left_ty, &right_ty, right.span())?;
+                self.equality(&left_ty, op.symbol(), span)?;
+                Ok(Type::Bool)
+            },
+            BinOp::Cons => {
+                let list_ty = list_of(left_ty);
+                self.unify(&list_ty, &right_ty, right.span())?;
+                Ok(self.apply(&list_ty))
+            },
+        }
+    }
+
+    fn visit_neg(&mut self, expr: &AstNode, span: &Span) -> Result<Type, Diagnostic> {
+        let ty = self.visit_node(expr)?;
+        self.overloaded(&ty, Overload::Numeric, "~", span)?;
+        Ok(self.apply(&ty))
+    }
+
+    fn visit_ref(&mut self, expr: &AstNode, _span: &Span) -> Result<Type, Diagnostic> {
+        let ty = self.visit_node(expr)?;
+        Ok(ref_of(self.apply(&ty)))
+    }
+
+    fn visit_deref(&mut self, expr: &AstNode, _span: &Span) -> Result<Type, Diagnostic> {
+        let ty = self.visit_node(expr)?;
+        let contents = self.fresh();
+        self.unify(&ref_of(contents.clone()), &ty, expr.span())?;
+        Ok(self.apply(&contents))
+    }
+
+    fn visit_assign(&mut self, target: &AstNode, value: &AstNode, _span: &Span) -> Result<Type, Diagnostic> {
+        let target_ty = self.visit_node(target)?;
+        let contents = self.fresh();
+        self.unify(&ref_of(contents.clone()), &target_ty, target.span())?;
+        let value_ty = self.visit_node(value)?;
+        self.unify(&contents, &value_ty, value.span())?;
+        Ok(Type::Unit)
+    }
+
+    fn visit_andalso(&mut self, left: &AstNode, right: &AstNode, _span: &Span) -> Result<Type, Diagnostic> {
+        self.infer_connective(left, right)
+    }
+
+    fn visit_orelse(&mut self, left: &AstNode, right: &AstNode, _span: &Span) -> Result<Type, Diagnostic> {
+        self.infer_connective(left, right)
+    }
+
+    fn visit_seq(&mut self, exprs: &[Box<AstNode>], _span: &Span) -> Result<Type, Diagnostic> {
+        let mut ty = Type::Unit;
+        for expr in exprs {
+            ty = self.visit_node(expr)?;
+        }
+        Ok(ty)
+    }
+
+    fn visit_raise(&mut self, expr: &AstNode, _span: &Span) -> Result<Type, Diagnostic> {
+        let ty = self.visit_node(expr)?;
+        self.unify(&exn(), &ty, expr.span())?;
+        // 'raise' never returns, so it fits wherever it is used
+        Ok(self.fresh())
+    }
+
+    fn visit_handle(&mut self, expr: &AstNode, arms: &Vec<(AstPattern, Box<AstNode>)>, span: &Span)
+        -> Result<Type, Diagnostic>
+    {
+        let ty = self.visit_node(expr)?;
+        let res = self.infer_arms(&exn(), arms)?;
+        self.unify(&ty, &res, span)?;
+        Ok(self.apply(&ty))
+    }
+
+    fn visit_app(&mut self, func: &AstNode, arg: &AstNode, _span: &Span) -> Result<Type, Diagnostic> {
+        let func_ty = self.visit_node(func)?;
+        let arg_ty = self.visit_node(arg)?;
+        let res_ty = self.fresh();
+        let expected = Type::Arrow(Box::new(arg_ty), Box::new(res_ty.clone()));
+        self.unify(&expected, &func_ty, func.span())?;
+        Ok(self.apply(&res_ty))
+    }
+
+    fn visit_id(&mut self, name: &str, span: &Span) -> Result<Type, Diagnostic> {
+        match self.lookup(name).cloned() {
+            Some(scheme) => Ok(self.instantiate(&scheme)),
+            None => Err(Diagnostic::error(format!("Unbound identifier '{}'", name))
+                .with_primary(*span, "not found in this scope")),
+        }
+    }
+
+    fn visit_var(&mut self, name: &str, span: &Span) -> Result<Type, Diagnostic> {
+        self.visit_id(name, span)
+    }
+
+    fn visit_tuple(&mut self, elements: &Vec<Box<AstNode>>, _span: &Span) -> Result<Type, Diagnostic> {
+        if elements.is_empty() {
+            return Ok(Type::Unit);
+        }
+        let mut types = Vec::new();
+        for element in elements {
+            types.push(self.visit_node(element)?);
+        }
+        Ok(Type::Product(types))
+    }
+
+    fn visit_record(&mut self, fields: &[(String, Box<AstNode>)], _span: &Span) -> Result<Type, Diagnostic> {
+        let mut types = Vec::new();
+        for (label, expr) in fields {
+            types.push((label.clone(), self.visit_node(expr)?));
+        }
+        Ok(Type::record(types))
+    }
+
+    fn visit_selector(&mut self, label: &str, span: &Span) -> Result<Type, Diagnostic> {
+        let record = self.fresh();
+        let field = self.fresh();
+        self.select_fields(&record, vec![(label.to_string(), field.clone())], span)?;
+        Ok(Type::Arrow(Box::new(record), Box::new(field)))
+    }
+
+    fn visit_list(&mut self, elements: &Vec<Box<AstNode>>, _span: &Span) -> Result<Type, Diagnostic> {
+        let elem_ty = self.fresh();
+        for element in elements {
+            let ty = self.visit_node(element)?;
+            self.unify(&elem_ty, &ty, element.span())?;
+        }
+        Ok(list_of(self.apply(&elem_ty)))
+    }
+
+    fn visit_literal(&mut self, lit: &LiteralValue, _span: &Span) -> Result<Type, Diagnostic> {
+        Ok(match lit {
+            LiteralValue::Integer(_) => Type::Int,
+            LiteralValue::Real(_) => Type::Real,
+            LiteralValue::Boolean(_) => Type::Bool,
+            LiteralValue::String(_) => Type::String,
+            LiteralValue::Char(_) => Type::Char,
+        })
+    }
+
+    fn visit_error(&mut self, _span: &Span) -> Result<Type, Diagnostic> {
+        // Broken code may have any type, so it does not add to the errors
+        Ok(self.fresh())
+    }
+
+    fn visit_literal_pattern(&mut self, lit: &LiteralValue, span: &Span) -> Result<Type, Diagnostic> {
+        self.visit_literal(lit, span)
+    }
+
+    fn visit_id_pattern(&mut self, name: &str, span: &Span) -> Result<Type, Diagnostic> {
+        self.bind_pattern_var(name, span)
+    }
+
+    fn visit_wildcard_pattern(&mut self, _span: &Span) -> Result<Type, Diagnostic> {
+        Ok(self.fresh())
+    }
+
+    fn visit_var_pattern(&mut self, name: &str, span: &Span) -> Result<Type, Diagnostic> {
+        self.bind_pattern_var(name, span)
+    }
+
+    fn visit_pair_pattern(&mut self, first: &AstPattern, second: &AstPattern, _span: &Span)
+        -> Result<Type, Diagnostic>
+    {
+        let first_ty = self.visit_pattern(first)?;
+        let second_ty = self.visit_pattern(second)?;
+        Ok(Type::Product(vec![first_ty, second_ty]))
+    }
+
+    fn visit_tuple_pattern(&mut self, elements: &Vec<AstPattern>, _span: &Span) -> Result<Type, Diagnostic> {
+        if elements.is_empty() {
+            return Ok(Type::Unit);
+        }
+        let mut types = Vec::new();
+        for element in elements {
+            types.push(self.visit_pattern(element)?);
+        }
+        Ok(Type::Product(types))
+    }
+
+    fn visit_constructor_pattern(&mut self, name: &str, arg: &Option<Box<AstPattern>>, span: &Span)
+        -> Result<Type, Diagnostic>
+    {
+        let takes_arg = self.constructor(name).unwrap_or(false);
+        let ty = self.visit_id(name, span)?;
+        match (arg, takes_arg) {
+            (Some(arg), true) => {
+                let arg_ty = self.visit_pattern(arg)?;
+                let res = self.fresh();
+                let expected = Type::Arrow(Box::new(arg_ty), Box::new(res.clone()));
+                self.unify(&ty, &expected, arg.span())?;
+                Ok(self.apply(&res))
+            },
+            (None, false) => Ok(ty),
+            (Some(_), false) => Err(Diagnostic::error(format!("Constructor '{}' does not take an argument", name))
+                .with_primary(*span, "applied to an argument here")),
+            (None, true) => Err(Diagnostic::error(format!("Constructor '{}' expects an argument", name))
+                .with_primary(*span, "missing its argument")),
+        }
+    }
+
+    fn visit_ref_pattern(&mut self, inner: &AstPattern, _span: &Span) -> Result<Type, Diagnostic> {
+        let ty = self.visit_pattern(inner)?;
+        Ok(ref_of(ty))
+    }
+
+    fn visit_record_pattern(&mut self, fields: &[(String, AstPattern)], flexible: bool, span: &Span)
+        -> Result<Type, Diagnostic>
+    {
+        let mut types = Vec::new();
+        for (label, pat) in fields {
+            types.push((label.clone(), self.visit_pattern(pat)?));
+        }
+        if !flexible {
+            return Ok(Type::record(types));
+        }
+        let record = self.fresh();
+        self.select_fields(&record, types, span)?;
+        Ok(record)
+    }
+
+    fn visit_cons_pattern(&mut self, head: &AstPattern, tail: &AstPattern, _span: &Span)
+        -> Result<Type, Diagnostic>
+    {
+        let list_ty = list_of(self.visit_pattern(head)?);
+        let tail_ty = self.visit_pattern(tail)?;
+        self.unify(&list_ty, &tail_ty, tail.span())?;
+        Ok(self.apply(&list_ty))
+    }
+
+    fn visit_nil_pattern(&mut self, _span: &Span) -> Result<Type, Diagnostic> {
+        Ok(list_of(self.fresh()))
+    }
+
+    fn visit_list_pattern(&mut self, elements: &Vec<AstPattern>, _span: &Span) -> Result<Type, Diagnostic> {
+        let elem_ty = self.fresh();
+        for element in elements {
+            let ty = self.visit_pattern(element)?;
+            self.unify(&elem_ty, &ty, element.span())?;
+        }
+        Ok(list_of(self.apply(&elem_ty)))
+    }
+}
+
+fn list_of(elem: Type) -> Type {
+    Type::Con("list".to_string(), vec![elem])
+}
+
+fn ref_of(contents: Type) -> Type {
+    Type::Con("ref".to_string(), vec![contents])
+}
+
+fn exn() -> Type {
+    Type::Con("exn".to_string(), Vec::new())
+}
+
+/// Collect the names bound by a pattern, in source order.
+fn pattern_names(pat: &AstPattern, names: &mut Vec<String>) {
+    match pat {
+        AstPattern::Id(name, _) | AstPattern::Var(name, _) => names.push(name.clone()),
+        AstPattern::Pair(first, second, _) | AstPattern::Cons(first, second, _) => {
+            pattern_names(first, names);
+            pattern_names(second, names);
+        },
+        AstPattern::List(elements, _) | AstPattern::Tuple(elements, _) => {
+            for element in elements {
+                pattern_names(element, names);
+            }
+        },
+        AstPattern::Constructor(_, arg, _) => {
+            if let Some(arg) = arg {
+                pattern_names(arg, names);
+            }
+        },
+        AstPattern::Ref(inner, _) => pattern_names(inner, names),
+        AstPattern::Record(fields, _, _) => {
+            for (_, pat) in fields {
+                pattern_names(pat, names);
+            }
+        },
+        AstPattern::Literal(..) | AstPattern::Wildcard(_) | AstPattern::Nil(_) => {},
+    }
+}
+
+fn free_vars(ty: &Type) -> HashSet<String> {
+    ordered_vars(ty).into_iter().collect()
+}
+
+/// The type variables of `ty` in order of first occurrence.
+fn ordered_vars(ty: &Type) -> Vec<String> {
+    fn walk(ty: &Type, vars: &mut Vec<String>) {
+        match ty {
+            Type::Var(name) if !vars.contains(name) => vars.push(name.clone()),
+            Type::Arrow(param, ret) => {
+                walk(param, vars);
+                walk(ret, vars);
+            },
+            Type::Product(types) | Type::Con(_, types) => {
+                types.iter().for_each(|t| walk(t, vars));
+            },
+            Type::Record(fields) => {
+                fields.iter().for_each(|(_, t)| walk(t, vars));
+            },
+            _ => {},
+        }
+    }
+    let mut vars = Vec::new();
+    walk(ty, &mut vars);
+    vars
+}
+
+fn rename(ty: &Type, mapping: &HashMap<String, Type>) -> Type {
+    match ty {
+        Type::Var(name) => mapping.get(name).cloned().unwrap_or_else(|| ty.clone()),
+        Type::Arrow(param, ret) => {
+            Type::Arrow(Box::new(rename(param, mapping)), Box::new(rename(ret, mapping)))
+        },
+        Type::Product(types) => {
+            Type::Product(types.iter().map(|t| rename(t, mapping)).collect())
+        },
+        Type::Con(name, args) => {
+            Type::Con(name.clone(), args.iter().map(|t| rename(t, mapping)).collect())
+        },
+        Type::Record(fields) => {
+            Type::Record(fields.iter().map(|(label, t)| (label.clone(), rename(t, mapping))).collect())
+        },
+        _ => ty.clone(),
+    }
+}
+
+/// Display types with their variables renamed to 'a, 'b, ... consistently
+/// across all of `types`, so related types in one message line up.
+pub fn pretty(types: &[&Type]) -> Vec<String> {
+    let mut mapping = HashMap::new();
+    for ty in types {
+        for var in ordered_vars(ty) {
+            if !mapping.contains_key(&var) {
+                let name = display_var(&var, var_name(mapping.len()));
+                mapping.insert(var, Type::Var(name));
+            }
+        }
+    }
+    types.iter().map(|ty| rename(ty, &mapping).to_string()).collect()
+}
+
+/// The name of the `n`th type variable when displayed: a, b, ..., z, a1, ...
+fn var_name(n: usize) -> String {
+    let letter = (b'a' + (n % 26) as u8) as char;
+    if n < 26 { letter.to_string() } else { format!("{}{}", letter, n / 26) }
+}
+
+/// Equality type variables, written ''a, are kept with a leading quote.
+fn is_equality_var(var: &str) -> bool {
+    var.starts_with('\'')
+}
+
+/// `name` as the display name of `var`, marked as an equality type variable
+/// if `var` is one.
+fn display_var(var: &str, name: String) -> String {
+    if is_equality_var(var) { format!("'{}", name) } else { name }
+}
@@ -0,0 +1,73 @@
+use std::fmt;
+
+/// A region of source text. `start` and `end` are byte offsets into the
+/// file, `line` and `col` are the 1-based position of `start`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub file: usize,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+    pub fn new(file: usize, start: usize, end: usize, line: usize, col: usize) -> Self {
+        Span { file, start, end, line, col }
+    }
+
+    /// The smallest span covering both `self` and `other`, where `other`
+    /// does not start before `self`.
+    pub fn to(&self, other: &Span) -> Span {
+        Span {
+            end: self.end.max(other.end),
+            ..*self
+        }
+    }
+
+    /// An empty span just past the end of this one.
+    pub fn after(&self) -> Span {
+        Span {
+            start: self.end,
+            col: self.col + (self.end - self.start),
+            ..*self
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+pub struct SourceFile {
+    pub name: String,
+    pub source: String,
+}
+
+/// The files of a compilation, indexed by the `file` id stored in each span.
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap { files: Vec::new() }
+    }
+
+    /// Register a file and return the id its spans should carry.
+    pub fn add(&mut self, name: &str, source: String) -> usize {
+        self.files.push(SourceFile { name: name.to_string(), source });
+        self.files.len() - 1
+    }
+
+    pub fn name(&self, file: usize) -> Option<&str> {
+        self.files.get(file).map(|f| f.name.as_str())
+    }
+
+    pub fn source(&self, file: usize) -> Option<&str> {
+        self.files.get(file).map(|f| f.source.as_str())
+    }
+}
@@ -22,6 +22,20 @@ pub struct Symbol {
     scope_ptr: Weak<RefCell<Scope>> // pointer to scope this symbol is defined in 
 }
 
+impl Symbol {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn ty(&self) -> &Type {
+        &self.ty
+    }
+
+    pub fn scope(&self) -> Option<Rc<RefCell<Scope>>> {
+        self.scope_ptr.upgrade()
+    }
+}
+
 impl Scope {
     fn new(parent_scope: Option<Weak<RefCell<Scope>>>) -> Self {
         Scope {
@@ -41,11 +55,25 @@ impl Scope {
             .find(|s| s.id == id)
             .cloned()
     }
+
+    pub fn parent(&self) -> Option<Rc<RefCell<Scope>>> {
+        self.parent_scope.as_ref().and_then(|p| p.upgrade())
+    }
+
+    pub fn children(&self) -> &[Rc<RefCell<Scope>>] {
+        &self.child_scopes
+    }
+}
+
+impl Default for SymbolTable {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SymbolTable {
     
-    fn new() -> Self {
+    pub fn new() -> Self {
         let global = Rc::new(RefCell::new(Scope::new(None)));
         SymbolTable {
             global_scope: global.clone(), // no parent scope for global
@@ -53,19 +81,49 @@ impl SymbolTable {
         }
     }
 
-    fn resolve(&self, id: &str) -> Option<Symbol> {
+    pub fn global_scope(&self) -> Rc<RefCell<Scope>> {
+        self.global_scope.clone()
+    }
+
+    pub fn resolve(&self, id: &str) -> Option<Symbol> {
         self.scope_stack
             .iter()
             .rev()
             .find_map(|scope| scope.borrow().resolve(id))
     }
 
-    fn push_scope(&mut self, scope: Rc<RefCell<Scope>>) {
+    /// Define a symbol in the innermost scope of the current traversal.
+    pub fn define(&mut self, id: &str, ty: Type) {
+        let current = self.current_scope();
+        let symbol = Symbol {
+            id: id.to_string(),
+            ty,
+            scope_ptr: Rc::downgrade(&current),
+        };
+        current.borrow_mut().symbols.push(symbol);
+    }
+
+    /// Open a new child scope of the current one and make it current.
+    pub fn enter_scope(&mut self) {
+        let current = self.current_scope();
+        let scope = Rc::new(RefCell::new(Scope::new(Some(Rc::downgrade(&current)))));
+        current.borrow_mut().add_child_scope(scope.clone());
+        self.push_scope(scope);
+    }
+
+    pub fn push_scope(&mut self, scope: Rc<RefCell<Scope>>) {
         self.scope_stack.push(scope)
     }
 
-    fn pop_scope(&mut self) {
+    pub fn pop_scope(&mut self) {
         self.scope_stack.pop();
     }
-}
 
+    /// The innermost scope of the current traversal.
+    pub fn current_scope(&self) -> Rc<RefCell<Scope>> {
+        self.scope_stack
+            .last()
+            .cloned()
+            .unwrap_or_else(|| self.global_scope.clone())
+    }
+}
@@ -0,0 +1,265 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use crate::ast::{compare_labels, is_tuple_labels, AstNode, AstPattern};
+use crate::lexer::escape_string;
+
+#[derive(Debug, Clone, Default)]
+pub enum Value {
+    #[default]
+    Unit,
+    Int(i64),
+    Real(f64),
+    Bool(bool),
+    String(String),
+    Char(char),
+    Tuple(Vec<Value>),
+    /// A record with its fields sorted by label. Records labelled `1` to
+    /// `n` are tuples instead, see `Value::record`.
+    Record(Vec<(String, Value)>),
+    List(Vec<Value>),
+    Closure(Rc<Closure>),
+    /// A datatype constructor that takes an argument, used as a function.
+    Constructor(String),
+    /// The `#label` function taking a field of a record or tuple.
+    Selector(String),
+    /// A datatype value: a constructor and its argument, if it has one.
+    Data(String, Option<Box<Value>>),
+    /// An exception constructor that takes an argument, used as a function.
+    ExnConstructor(Rc<Exception>),
+    /// A value of type `exn`: the exception and its argument, if it has one.
+    Exn(Rc<Exception>, Option<Box<Value>>),
+    /// A mutable cell created by `ref`, shared by every copy of the value.
+    Ref(Rc<RefCell<Value>>),
+}
+
+/// An exception made by running an `exception` declaration. Each run makes
+/// a new one, so exceptions are told apart by their address, not by name.
+#[derive(Debug)]
+pub struct Exception {
+    pub name: String,
+}
+
+/// A function value: the clauses of a `fn`/`fun` together with the
+/// environment they were defined in.
+pub struct Closure {
+    pub clauses: Vec<(AstPattern, Box<AstNode>)>,
+    pub env: Env,
+}
+
+// The captured environment of a recursive function contains the function
+// itself, so it is left out.
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Closure")
+            .field("clauses", &self.clauses)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Value {
+    /// A record with `fields`, which is unit if there are none and a tuple if
+    /// the labels are those of one.
+    pub fn record(mut fields: Vec<(String, Value)>) -> Value {
+        fields.sort_by(|(a, _), (b, _)| compare_labels(a, b));
+        if fields.is_empty() {
+            Value::Unit
+        } else if is_tuple_labels(fields.iter().map(|(label, _)| label.as_str())) {
+            Value::Tuple(fields.into_iter().map(|(_, value)| value).collect())
+        } else {
+            Value::Record(fields)
+        }
+    }
+
+    /// The field `label` of a record or tuple.
+    pub fn field(&self, label: &str) -> Option<&Value> {
+        match self {
+            Value::Record(fields) => fields.iter().find(|(l, _)| l == label).map(|(_, value)| value),
+            Value::Tuple(values) => label
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| values.get(i.checked_sub(1)?)),
+            _ => None,
+        }
+    }
+
+    /// Structural equality as used by SML's `=`. The type checker makes sure
+    /// that neither side contains a function.
+    pub fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Unit, Value::Unit) => true,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Real(a), Value::Real(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Char(a), Value::Char(b)) => a == b,
+            (Value::Tuple(a), Value::Tuple(b)) | (Value::List(a), Value::List(b)) => {
+                if a.len() != b.len() {
+                    return false;
+                }
+                for (x, y) in a.iter().zip(b) {
+                    if !x.equals(y) {
+                        return false;
+                    }
+                }
+                true
+            },
+            (Value::Record(a), Value::Record(b)) => {
+                if a.len() != b.len() {
+                    return false;
+                }
+                for ((l1, x), (l2, y)) in a.iter().zip(b) {
+                    if l1 != l2 || !x.equals(y) {
+                        return false;
+                    }
+                }
+                true
+            },
+            (Value::Data(a, x), Value::Data(b, y)) => match (x, y) {
+                (Some(x), Some(y)) if a == b => x.equals(y),
+                _ => a == b && x.is_none() && y.is_none(),
+            },
+            (Value::Exn(a, x), Value::Exn(b, y)) => match (x, y) {
+                (Some(x), Some(y)) if Rc::ptr_eq(a, b) => x.equals(y),
+                _ => Rc::ptr_eq(a, b) && x.is_none() && y.is_none(),
+            },
+            // Cells are equal only if they are the same cell
+            (Value::Ref(a), Value::Ref(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Unit => write!(f, "()"),
+            Value::Int(n) if *n < 0 => write!(f, "~{}", n.unsigned_abs()),
+            Value::Int(n) => write!(f, "{}", n),
+            // SML spells the minus sign '~' and always shows a real's point
+            Value::Real(x) => write!(f, "{}", format!("{:?}", x).replace('e', "E").replace('-', "~")),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::String(s) => write!(f, "\"{}\"", escape_string(s)),
+            Value::Char(c) => write!(f, "#\"{}\"", escape_string(&c.to_string())),
+            Value::Tuple(values) => {
+                write!(f, "(")?;
+                for (i, v) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                write!(f, ")")
+            },
+            Value::Record(fields) => {
+                write!(f, "{{")?;
+                for (i, (label, v)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{} = {}", label, v)?;
+                }
+                write!(f, "}}")
+            },
+            Value::List(values) => {
+                write!(f, "[")?;
+                for (i, v) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                write!(f, "]")
+            },
+            Value::Closure(_) | Value::Constructor(_) | Value::ExnConstructor(_) | Value::Selector(_) => {
+                write!(f, "fn")
+            },
+            Value::Data(name, arg) => write_applied(f, name, arg.as_deref()),
+            Value::Exn(exn, arg) => write_applied(f, &exn.name, arg.as_deref()),
+            Value::Ref(cell) => write_applied(f, "ref", Some(&cell.borrow())),
+        }
+    }
+}
+
+/// Write a constructor applied to `arg`, if any, putting the argument in
+/// parentheses if it is an application itself.
+fn write_applied(f: &mut fmt::Formatter, name: &str, arg: Option<&Value>) -> fmt::Result {
+    match arg {
+        None => write!(f, "{}", name),
+        Some(arg @ (Value::Data(_, Some(_)) | Value::Exn(_, Some(_)) | Value::Ref(_))) => {
+            write!(f, "{} ({})", name, arg)
+        },
+        Some(arg) => write!(f, "{} {}", name, arg),
+    }
+}
+
+/// Runtime environment: a chain of frames, innermost first. Every
+/// declaration opens a new frame, so a closure never observes bindings
+/// that shadow its free variables after it was created.
+#[derive(Clone)]
+pub struct Env(Option<Rc<Frame>>);
+
+pub struct Frame {
+    vars: RefCell<HashMap<String, Value>>,
+    parent: Env,
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Env {
+    /// An environment with a single, empty frame.
+    pub fn new() -> Self {
+        Env(None).extend()
+    }
+
+    /// A new, empty frame on top of this environment.
+    pub fn extend(&self) -> Env {
+        Env(Some(Rc::new(Frame {
+            vars: RefCell::new(HashMap::new()),
+            parent: self.clone(),
+        })))
+    }
+
+    /// Bind `name` in the innermost frame. Recursive functions are defined by
+    /// extending first and then binding the closure that captured the frame.
+    pub fn define(&self, name: &str, value: Value) {
+        if let Some(frame) = &self.0 {
+            frame.vars.borrow_mut().insert(name.to_string(), value);
+        }
+    }
+
+    /// The bindings of the frames added on top of `base`, outermost first so
+    /// that defining them in order lets inner ones shadow outer ones.
+    pub fn defined_since(&self, base: &Env) -> Vec<(String, Value)> {
+        let mut frames = Vec::new();
+        let mut env = self;
+        while let Some(frame) = &env.0 {
+            if matches!(&base.0, Some(base) if Rc::ptr_eq(frame, base)) {
+                break;
+            }
+            frames.push(frame);
+            env = &frame.parent;
+        }
+        frames
+            .into_iter()
+            .rev()
+            .flat_map(|frame| frame.vars.borrow().clone())
+            .collect()
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<Value> {
+        let mut env = self;
+        while let Some(frame) = &env.0 {
+            if let Some(value) = frame.vars.borrow().get(name) {
+                return Some(value.clone());
+            }
+            env = &frame.parent;
+        }
+        None
+    }
+}
@@ -0,0 +1,16 @@
+val id = fn x => x
+val pair = (id 1, id true)
+val compose = fn f => fn g => fn x => f (g x)
+val twice = fn f => fn x => f (f x)
+val add = fn (x, y) => x + y
+val xs = [1, 2, 3]
+val swap : 'a * 'b -> 'b * 'a = fn (a, b) => (b, a)
+val k = let val c = fn x => fn y => x in c 1 true end
+fun fact n = if n <= 1 then 1 else n * fact (n - 1)
+
+(* Functions using '=' only take types that admit equality, written ''a *)
+fun member x [] = false
+  | member x (y :: ys) = x = y orelse member x ys
+val found = member "b" ["a", "b"]
+val cells = let val r = ref (fn x => x + 1) in r = r end
+val same : ''a * ''a -> bool = fn (x, y) => x = y
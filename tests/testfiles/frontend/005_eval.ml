@@ -0,0 +1,12 @@
+val x = 42
+val add = fn (a, b) => a + b
+val sum = add (x, 8)
+fun fact n = if n <= 1 then 1 else n * fact (n - 1)
+val f10 = fact 10
+val curried = fn a => fn b => a * b
+val six = curried 2 3
+val neg = 0 - 7
+val q = neg / 2
+val (p, r) = (sum > 40, [1, 2, 3])
+val k = let val c = 10 in c * c end
+val unit = ()
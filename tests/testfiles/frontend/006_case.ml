@@ -0,0 +1,6 @@
+val p = (1, true)
+val a = case p of (n, b) => if b then n else 0
+val swap = fn q => case q of (x, y) => (y, x)
+val first = case swap p of (_, n) => n + 1
+val nested = case (2, (3, 4)) of (x, (y, z)) => x * y * z
+val twice = case (fn x => x * 2) of f => f (f 3)
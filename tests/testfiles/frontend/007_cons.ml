@@ -0,0 +1,10 @@
+val xs = 1 :: 2 :: 3 :: nil
+val ys = 0 :: [1, 2]
+val empty = nil
+val head = fn x :: _ => x
+val first = head xs
+val second = case xs of _ :: y :: _ => y | _ => 0
+val pair = case ys of [a, b, c] => (a, c) | _ => (0, 0)
+val rest = case xs of nil => nil | (_ :: tl) => tl
+fun len l = case l of nil => 0 | _ :: tl => 1 + len tl
+val n = len (4 :: xs)
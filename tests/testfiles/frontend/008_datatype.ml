@@ -0,0 +1,23 @@
+datatype color = Red | Green | Blue
+datatype 'a tree = Leaf | Node of 'a tree * 'a * 'a tree
+datatype 'a mylist = NIL | CONS of 'a * 'a mylist
+val c = Green
+val isRed = fn Red => true | _ => false
+val t = Node (Node (Leaf, 1, Leaf), 2, Leaf)
+fun size t = case t of Leaf => 0 | Node (l, _, r) => size l + 1 + size r
+val n = size t
+fun insert x = fn Leaf => Node (Leaf, x, Leaf) | Node (l, y, r) => if x < y then Node (insert x l, y, r) else Node (l, y, insert x r)
+val t2 = insert 5 t
+val xs = CONS (1, CONS (2, NIL))
+fun sum l = case l of NIL => 0 | CONS (h, tl) => h + sum tl
+val total = sum xs
+val unit = case () of () => Blue
+
+(* Constructors declared in a let or local go out of scope with it *)
+val v = let datatype hidden = A | B in 1 end
+fun first A = 1
+val one = first 2
+local datatype key = K in val k = 3 end
+val three = (fn K => K) k
+val annotated : int tree list = [Leaf, t]
+val cell : color option ref = ref (some Red)
@@ -0,0 +1,10 @@
+val a = some 1
+val b = none
+val c = some (some true)
+val get = fn some x => x | none => 0
+val x = get a
+val y = get b
+val inner = case c of some (some v) => v | _ => false
+fun last l = case l of nil => none | [h] => some h | _ :: t => last t
+val found = last [1, 2, 3, 4]
+val missing = last nil
@@ -0,0 +1,8 @@
+val double = fn 0 => 1 | n => n * 2
+val a = double 0
+val b = double 21
+val neg = fn true => false | false => true
+val c = neg true
+fun fib n = case n of 0 => 0 | 1 => 1 | _ => fib (n - 1) + fib (n - 2)
+val e = fib 10
+val (f, 2) = (5, 2)
@@ -0,0 +1,16 @@
+val s = "hello"
+val esc = "tab\there \"quoted\" back\\slash\n"
+val dec = "\065\066\067"
+val uni = "A\u00e9"
+val ctrl = "\^A"
+val gap = "one \
+          \two"
+val c = #"a"
+val nl = #"\n"
+val isA = fn #"a" => true | _ => false
+val t = isA c
+val greet = fn "world" => 1 | _ => 0
+val g = greet "world"
+val escape = "\027[0m\127ā"
+val quote = #"\""
+val bell = #"\a"
@@ -0,0 +1,7 @@
+(* A comment before any declaration *)
+val x = 1 (* trailing comment *)
+(* comments (* can be nested *)
+   and span
+   several lines *)
+val y = x + (* inline *) 2
+val s = "(* not a comment *)"
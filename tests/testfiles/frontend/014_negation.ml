@@ -0,0 +1,18 @@
+val a = ~5
+val b = ~a
+val c = ~(a * 2) + 1
+val d = 3 - ~2
+val abs = fn n => if n < 0 then ~n else n
+val e = abs ~7
+val sign = fn ~1 => "negative one" | 0 => "zero" | _ => "other"
+val f = sign ~1
+val g = [~1, ~2, 3]
+val h = ~9223372036854775808
+
+(* '~' is a function, on ints or on reals *)
+fun map f [] = []
+  | map f (x :: xs) = f x :: map f xs
+val negated = map ~ [1, 2]
+val reals = map ~ [1.5, ~2.0]
+val neg = op ~
+val twice = (op ~) (~ 3)
@@ -0,0 +1,15 @@
+val pi = 3.14159
+val small = 1.5e~3
+val big = 2E10
+val neg = ~0.5
+val area = fn r => pi * r * r
+val a = area 2.0
+val half = 7.0 / 2.0
+val less = 1.5 < 2.5
+val sq = fn x => x * x
+val b = sq 3
+fun sum l = case l of nil => 0.0 | h :: t => h + sum t
+val total = sum [1.0, 2.5, ~0.5]
+val r : real = 1.0 + 2.0
+val i = 1 + 2
+val m = let val inc = fn x => x + 1.0 in inc 1.0 end
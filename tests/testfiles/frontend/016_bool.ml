@@ -0,0 +1,15 @@
+val t = true andalso not false
+val f = false orelse false
+val prec = true orelse false andalso false
+val short = false andalso 1 / 0 < 1
+val short2 = true orelse 1 / 0 < 1
+val inRange = fn n => 0 < n andalso n < 10 orelse n < ~100
+val a = inRange 5
+val b = inRange 50
+val c = not (inRange ~200)
+
+(* 'not' is an ordinary function *)
+fun map f [] = []
+  | map f (x :: xs) = f x :: map f xs
+val flipped = map not [true, false]
+val negate = not
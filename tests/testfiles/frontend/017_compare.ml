@@ -0,0 +1,21 @@
+val lt = (1 < 2, 2 < 1, 1 < 1)
+val le = (1 <= 2, 2 <= 1, 1 <= 1)
+val gt = (1 > 2, 2 > 1, 1 > 1)
+val ge = (1 >= 2, 2 >= 1, 1 >= 1)
+val eq = (1 = 1, 1 = 2)
+val ne = (1 <> 1, 1 <> 2)
+val reals = (1.5 < 2.5, 2.5 >= 2.5)
+val strings = ("a" = "a", "a" <> "b")
+val structural = ((1, [true]) = (1, [true]), [1, 2] <> [1, 2])
+val options = (some 1 = some 1, none = some 2)
+val prec = 1 + 1 = 2 andalso 3 * 2 <> 5
+val max = fn (a, b) => if a >= b then a else b
+val m = max (3, 7)
+fun isZero n = n = 0
+val z = isZero 0
+val words = ("apple" < "banana", "b" <= "abc", "" < "a", "abc" >= "abc")
+val chars = (#"a" < #"b", #"z" > #"a", #"A" >= #"a", #"q" <= #"q")
+fun maxString (a, b) : string = if a >= b then a else b
+val longest = maxString ("pear", "fig")
+fun lessThan (a, b) = a < b
+val defaulted = lessThan (1, 2)
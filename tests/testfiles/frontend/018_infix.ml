@@ -0,0 +1,33 @@
+(* Operators can be declared infix, with a precedence and associativity *)
+fun append (xs, ys) = case xs of
+    [] => ys
+  | x :: rest => x :: append (rest, ys)
+infixr 5 @
+val op @ = append
+val xs = [1, 2] @ [3] @ [4, 5]
+val ys = 0 :: [1] @ [2]
+
+infixl 6 ++
+fun op ++ (a, b) = a * 10 + b
+val digits = 1 ++ 2 ++ 3
+val mixed = 1 ++ 2 * 3
+
+infixr 6 --
+fun -- (a, b) = a - b
+val right = 10 -- 4 -- 3
+
+val plus = op +
+val sum = plus (1, 2)
+val cons = op :: (1, [2])
+val pairs = op @ ([1], [2])
+
+(* Fixity declared inside a let only holds until its end *)
+fun max (a, b) = if a > b then a else b
+val local_infix = let infix 7 max in 1 + 5 max 2 end
+val m = max (3, 4)
+
+(* An infix operator can be defined between its two arguments *)
+infixr 5 @@
+fun [] @@ ys = ys
+  | (x :: rest) @@ ys = x :: (rest @@ ys)
+val joined = [1, 2] @@ [3] @@ [4]
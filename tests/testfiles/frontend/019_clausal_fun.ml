@@ -0,0 +1,35 @@
+(* Functions defined by clauses, each repeating the function's name *)
+fun fact 0 = 1
+  | fact n = n * fact (n - 1)
+val f = fact 5
+
+fun add x y = x + y
+val inc = add 1
+val three = inc 2
+
+fun zip [] _ = []
+  | zip _ [] = []
+  | zip (x :: xs) (y :: ys) = (x, y) :: zip xs ys
+val pairs = zip [1, 2, 3] [true, false]
+
+datatype tree = Leaf | Node of tree * int * tree
+fun insert Leaf n = Node (Leaf, n, Leaf)
+  | insert (Node (l, v, r)) n =
+      if n < v then Node (insert l n, v, r) else Node (l, v, insert r n)
+fun sum Leaf = 0
+  | sum (Node (l, v, r)) = sum l + v + sum r
+val total = sum (insert (insert (insert Leaf 2) 1) 3)
+
+fun fold f acc [] = acc
+  | fold f acc (x :: xs) = fold f (f (acc, x)) xs
+val folded = fold (op +) 0 [1, 2, 3, 4]
+
+fun getOr none d = d
+  | getOr (some x) _ = x
+val g = (getOr (some 3) 0, getOr none 7)
+
+(* A clause may give the type of the function's result *)
+fun pow b 0 : int = 1
+  | pow b e : int = b * pow b (e - 1)
+val p = pow 2 10
+fun first (x, _) : 'a = x
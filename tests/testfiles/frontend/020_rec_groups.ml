@@ -0,0 +1,26 @@
+(* Functions declared together with 'and' can call each other *)
+fun isEven 0 = true
+  | isEven n = isOdd (n - 1)
+and isOdd 0 = false
+  | isOdd n = isEven (n - 1)
+val e = (isEven 10, isOdd 7, isEven 3)
+
+val rec count = fn 0 => [] | n => n :: count (n - 1)
+val c = count 3
+
+val rec ping = fn n => if n = 0 then "ping" else pong (n - 1)
+and pong = fn n => if n = 0 then "pong" else ping (n - 1)
+val p = (ping 4, ping 3)
+
+(* The group is generalised as a whole *)
+fun mapOdd f [] = []
+  | mapOdd f (x :: xs) = f x :: mapEven f xs
+and mapEven f [] = []
+  | mapEven f (x :: xs) = x :: mapOdd f xs
+val m = mapOdd (fn x => x * 10) [1, 2, 3, 4]
+
+val local_group = let
+    fun down 0 = 0
+      | down n = up (n - 1)
+    and up n = down n
+  in down 5 end
@@ -0,0 +1,36 @@
+exception Empty
+exception Fail of string
+
+fun head [] = raise Empty
+  | head (x :: _) = x
+val h = head [1, 2] handle Empty => 0
+val e = head [] handle Empty => 0
+
+fun check n = if n < 0 then raise Fail "negative" else n
+val msg = (if check ~1 = 0 then "zero" else "fine") handle Fail s => s
+val unhandled = (check 3 handle Fail _ => 0) + 1
+
+(* Handlers that do not match let the exception through *)
+val outer = ((raise Fail "inner") handle Empty => "empty") handle Fail s => s
+
+(* Built-in exceptions *)
+val div0 = 10 / 0 handle Div => ~1
+fun only1 1 = "one"
+val nomatch = only1 2 handle Match => "no match"
+val bind = let val [x] = [1, 2] in x end handle Bind => 0
+val any = (1 / 0) handle _ => 42
+
+(* Each run of an exception declaration makes a new exception *)
+fun fresh () = let exception E in raise E end
+exception E
+val generative = fresh () handle E => "outer" | _ => "fresh"
+fun wrap n = let exception W of int in (W n, fn W m => m | _ => ~1) end
+val (w1, unwrap1) = wrap 1
+val (w2, unwrap2) = wrap 2
+val unwrapped = (unwrap1 w1, unwrap1 w2)
+
+(* Integer overflow raises the built-in Overflow *)
+val maxInt = 9223372036854775807
+val wrapped = (maxInt + 1) handle Overflow => 0
+val negated = (~ (~maxInt - 1)) handle Overflow => 1
+val which = (maxInt * 2; "none") handle Div => "div" | Overflow => "overflow"
@@ -0,0 +1,34 @@
+val r = ref 1
+val before = !r
+val set = r := !r + 41
+val after = !r
+
+fun counter start =
+  let
+    val count = ref start
+  in
+    fn () => let val _ = count := !count + 1 in !count end
+  end
+val next = counter 10
+val a = next ()
+val b = next ()
+
+fun get (ref x) = x
+val g = get (ref "cell")
+
+val alias = let val c = ref 0 in let val d = c in let val _ = d := 5 in !c end end end
+val same = (r = r, ref 1 = ref 1)
+val nested = ref (some 3)
+
+(* The value restriction keeps refs monomorphic *)
+val cells = ref []
+val fill = cells := [true]
+val poly = fn x => x
+val id = (poly 1, poly true)
+
+(* 'ref' and '!' are ordinary functions *)
+fun map f [] = []
+  | map f (x :: xs) = f x :: map f xs
+val boxes = map ref [1, 2]
+val contents = map ! [ref 1]
+val read = !
@@ -0,0 +1,14 @@
+val r = ref 0
+val last = (r := 1; r := !r + 1; !r)
+val unitSeq = (r := 10; ())
+
+fun bump n = let val c = ref n in c := !c + 1; c := !c * 2; !c end
+val b = bump 4
+
+(* Evaluated left to right, so the log is built in order *)
+val log = ref []
+fun note x = log := x :: !log
+val order = (note 1; note 2; note 3; !log)
+
+exception Stop
+val stopped = (note 4; raise Stop; note 5; !log) handle Stop => !log
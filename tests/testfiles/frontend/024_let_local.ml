@@ -0,0 +1,42 @@
+val sum = let val a = 1 val b = 2 in a + b end
+val pair = let val x = 3; fun double y = y * 2; val z = double x in (x, z) end
+
+(* Later declarations see earlier ones and may shadow them *)
+val shadow = let val n = 1 val n = n + 10 in n end
+
+local
+  fun helper x = x * x
+  val offset = 1
+in
+  fun squarePlus x = helper x + offset
+  val nine = helper 3
+end
+val ten = squarePlus 3
+
+(* The public part of a local is polymorphic like any top-level binding *)
+local
+  val unused = 0
+in
+  fun ident x = x
+end
+val both = (ident 1, ident true)
+
+local
+  infix 6 ++
+  fun op ++ (a, b) = a + b + 1
+in
+  fun plusOne (a, b) = a ++ b
+end
+val eleven = plusOne (5, 5)
+
+val nested = let local val h = 4 in val k = h + 1 end in k * 2 end
+
+(* Datatypes and exceptions of the private part stay private *)
+local
+  datatype sign = Pos | Neg
+  exception Zero
+  fun sign n = if n = 0 then raise Zero else if n > 0 then Pos else Neg
+in
+  fun isPositive n = (sign n = Pos) handle Zero => false
+end
+val positive = (isPositive 3, isPositive 0)
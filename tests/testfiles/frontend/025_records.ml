@@ -0,0 +1,29 @@
+val alice = {name = "Alice", age = 30}
+val name = #name alice
+val older = {name = #name alice, age = #age alice + 1}
+
+(* Fields are sorted by label, so the order they are written in does not matter *)
+val same = alice = {age = 30, name = "Alice"}
+
+fun greet {name, age} = if age > 18 then (name, "adult") else (name, "minor")
+val hello = greet alice
+
+(* A flexible pattern or a selector needs the record type from its context *)
+val ageOf : {name: string, age: int} -> int = fn {age, ...} => age
+val bobAge = ageOf {age = 25, name = "Bob"}
+fun map f [] = []
+  | map f (x :: xs) = f x :: map f xs
+val names = map #name [alice, older]
+
+(* Tuples are records labelled 1 to n *)
+val first = #1 (1, true)
+val third = #3 ("a", 2.5, #"c")
+val pair = {2 = "two", 1 = 1}
+val swap = fn {1 = a, 2 = b} => (b, a)
+val swapped = swap (1, "one")
+val single = {1 = 5}
+
+val nested = {inner = {value = 7}, label = "n"}
+val seven = #value (#inner nested)
+val {inner = {value = v}, ...} = nested
+val unitRecord = {}
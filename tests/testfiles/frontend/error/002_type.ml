@@ -0,0 +1 @@
+val x : int = true
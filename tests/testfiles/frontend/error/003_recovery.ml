@@ -0,0 +1,6 @@
+val a = (1 + ) * 2
+val b = 10
+val c = [1, 2,
+val d = let val x = in x end
+fun f = 3
+val e = b + 1
@@ -0,0 +1,3 @@
+val ok = "fine"
+val bad = "no closing quote
+val c = #"ab"
@@ -0,0 +1,3 @@
+val a = 1
+(* this comment (* is nested *) but never closed
+val b = 2
@@ -0,0 +1,3 @@
+val a = 1.2.3
+val b = 2 § 3
+val c = "bad \q escape"
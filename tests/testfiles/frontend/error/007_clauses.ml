@@ -0,0 +1,11 @@
+fun f 0 = 1
+  | g n = n
+fun h x y = x
+  | h z = z
+fun k x => x
+fun m 0 : int = 0
+  | m _ : bool = true
+fun p 0 = 1
+  | p (0 = 2
+val q = fun x => x + 1
+val r = 1
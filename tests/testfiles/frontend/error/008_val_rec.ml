@@ -0,0 +1,4 @@
+val rec x = 1
+fun f 0 = g 0
+  | f n = n
+and g n = f true
@@ -0,0 +1,4 @@
+exception Oops of int
+fun f 0 = raise Oops 3
+  | f n = f (n - 1)
+val x = f 2
@@ -0,0 +1,3 @@
+val r = ref []
+val a = r := [1]
+val b = r := [true]
@@ -0,0 +1,6 @@
+local
+  val secret = 42
+in
+  val exposed = secret + 1
+end
+val leaked = secret
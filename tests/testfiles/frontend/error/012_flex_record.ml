@@ -0,0 +1,2 @@
+(* The record type of r can not be worked out from #age alone *)
+fun getAge r = #age r
@@ -0,0 +1,5 @@
+(* An unclosed bracket does not hide the errors of the declarations after it *)
+val c = [1, 2
+val d = 1 +
+val e = (3, 4
+val f = let val q = in 1 end
@@ -0,0 +1,2 @@
+(* Type constructors must be declared before they are used *)
+datatype shape = Circle of real | Poly of point list
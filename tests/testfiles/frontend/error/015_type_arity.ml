@@ -0,0 +1,2 @@
+(* A type constructor must be given as many arguments as it takes *)
+datatype 'a tree = Leaf | Node of tree * 'a * tree
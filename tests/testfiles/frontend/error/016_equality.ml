@@ -0,0 +1,2 @@
+(* Functions, reals and exceptions do not admit equality *)
+val b = (fn x => x) = (fn y => y)